@@ -1,23 +1,68 @@
+use std::cmp;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::mem;
+use std::slice;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct BST {
     root: Link,
 }
 
+// Besides its element, every node keeps aggregates of its whole subtree,
+// so range queries can use a subtree at once when it is entirely inside
+// (or outside) the range.
 #[derive(Debug)]
 struct Node {
     elem: i32,
     left: Link,
     right: Link,
+    size: usize,
+    sum: i64,
+    min: i32,
+    max: i32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 enum Link {
+    #[default]
     Empty,
     More(Box<Node>),
 }
 
+/// Extra information to show on every node of a DOT graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Annotation {
+    /// The height of the node's subtree, where a leaf has height 1.
+    Height,
+    /// The number of elements in the node's subtree.
+    Size,
+    /// The height of the right subtree minus the height of the left one.
+    Balance,
+}
+
+/// A summary of the shape of a BST.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub size: usize,
+    /// The number of nodes on the longest path from the root, or 0 for an
+    /// empty tree.
+    pub height: usize,
+    /// How many nodes have each balance factor, the height of their right
+    /// subtree minus the height of their left one.
+    pub balance: BTreeMap<isize, usize>,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "size {}, height {}, balance factors", self.size, self.height)?;
+        for (factor, count) in &self.balance {
+            write!(f, " [{}: {}]", factor, count)?;
+        }
+        Ok(())
+    }
+}
+
 impl BST {
     pub fn new() -> BST {
         BST { root: Link::Empty }
@@ -34,29 +79,147 @@ impl BST {
     pub fn search(&self, elem: i32) -> bool {
         self.root.search(elem)
     }
+
+    /// Delete an element from the BST. Return true
+    /// if the element was in the BST.
+    pub fn delete(&mut self, elem: i32) -> bool {
+        self.root.delete(elem)
+    }
+
+    /// Return the number of elements in the BST.
+    pub fn len(&self) -> usize {
+        self.root.size()
+    }
+
+    /// Return true if the BST has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.root.size() == 0
+    }
+
+    /// Return the sum of all elements within [lo, hi].
+    /// Takes time proportional to the height of the tree.
+    pub fn range_sum(&self, lo: i32, hi: i32) -> i64 {
+        self.root.range_aggregate(lo, hi).1
+    }
+
+    /// Return the number of elements within [lo, hi].
+    /// Takes time proportional to the height of the tree.
+    pub fn range_count(&self, lo: i32, hi: i32) -> usize {
+        self.root.range_aggregate(lo, hi).0
+    }
+
+    /// Draw the BST sideways, one element per line, with the root on the
+    /// left and larger elements above smaller ones:
+    ///
+    /// ```text
+    ///     /-- 3
+    /// 2
+    ///     \-- 1
+    /// ```
+    pub fn to_ascii(&self) -> String {
+        let mut out = String::new();
+        self.root.draw_sideways("", Side::Root, &mut out);
+        out
+    }
+
+    /// Export the BST as a Graphviz DOT graph.
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with(&[])
+    }
+
+    /// Export the BST as a Graphviz DOT graph, adding the given
+    /// annotations to the label of every node.
+    pub fn to_dot_with(&self, annotations: &[Annotation]) -> String {
+        let mut shapes = vec![];
+        self.root.measure(&mut shapes);
+        let mut out = String::from("digraph BST {\n");
+        let mut next_id = 0;
+        self.root.write_dot(annotations, &mut shapes.iter(), &mut next_id, &mut out);
+        out.push_str("}\n");
+        out
+    }
+
+    /// Summarize the shape of the BST.
+    pub fn stats(&self) -> Stats {
+        let mut shapes = vec![];
+        let root = self.root.measure(&mut shapes);
+        let mut stats = Stats {
+            size: root.size,
+            height: root.height,
+            balance: BTreeMap::new()
+        };
+        for shape in &shapes {
+            *stats.balance.entry(shape.balance).or_insert(0) += 1;
+        }
+        stats
+    }
+}
+
+// The size, height and balance factor of a subtree, which `measure` finds
+// for every node in one pass.
+#[derive(Clone, Copy, Debug, Default)]
+struct Shape {
+    size: usize,
+    height: usize,
+    balance: isize,
+}
+
+// Which side of its parent a node is drawn on.
+#[derive(Clone, Copy, PartialEq)]
+enum Side {
+    Root,
+    Left,
+    Right,
+}
+
+impl Node {
+    fn new(elem: i32) -> Node {
+        Node {
+            elem,
+            left: Link::Empty,
+            right: Link::Empty,
+            size: 1,
+            sum: i64::from(elem),
+            min: elem,
+            max: elem
+        }
+    }
+
+    // Recompute the aggregates from the children, which must be up to date.
+    fn update(&mut self) {
+        self.size = 1 + self.left.size() + self.right.size();
+        self.sum = i64::from(self.elem) + self.left.sum() + self.right.sum();
+        self.min = match self.left {
+            Link::Empty => self.elem,
+            Link::More(ref left) => left.min
+        };
+        self.max = match self.right {
+            Link::Empty => self.elem,
+            Link::More(ref right) => right.max
+        };
+    }
 }
 
 impl Link {
     pub fn insert(&mut self, elem: i32) -> bool {
         match *self {
             Link::Empty => {
-                let node = Node {
-                    elem: elem,
-                    left: Link::Empty,
-                    right: Link::Empty
-                };
-                mem::replace(self, Link::More(Box::new(node)));
-                return true;
+                *self = Link::More(Box::new(Node::new(elem)));
+                true
             },
             Link::More(ref mut boxed_node) => {
                 let node = boxed_node.as_mut();
-                if elem < node.elem {
+                let inserted = if elem < node.elem {
                     node.left.insert(elem)
                 } else if elem > node.elem {
                     node.right.insert(elem)
                 } else {
                     false
+                };
+                if inserted {
+                    node.update();
                 }
+                inserted
             }
         }
     }
@@ -76,11 +239,254 @@ impl Link {
             }
         }
     }
+
+    pub fn delete(&mut self, elem: i32) -> bool {
+        match *self {
+            Link::Empty => false,
+            Link::More(ref mut node) if elem != node.elem => {
+                let deleted = if elem < node.elem {
+                    node.left.delete(elem)
+                } else {
+                    node.right.delete(elem)
+                };
+                if deleted {
+                    node.update();
+                }
+                deleted
+            },
+            Link::More(_) => {
+                self.unlink();
+                true
+            }
+        }
+    }
+
+    // Remove the node of this link, putting its in-order successor (or its
+    // only child) in its place.
+    fn unlink(&mut self) {
+        if let Link::More(mut node) = mem::take(self) {
+            *self = match (mem::take(&mut node.left), mem::take(&mut node.right)) {
+                (Link::Empty, right) => right,
+                (left, Link::Empty) => left,
+                (left, mut right) => match right.take_min() {
+                    Some(mut next) => {
+                        next.left = left;
+                        next.right = right;
+                        next.update();
+                        Link::More(next)
+                    },
+                    None => left
+                }
+            };
+        }
+    }
+
+    // Detach the node holding the smallest element of this subtree,
+    // updating the aggregates along the way.
+    fn take_min(&mut self) -> Option<Box<Node>> {
+        if let Link::More(ref mut node) = *self {
+            if let Link::More(_) = node.left {
+                let min = node.left.take_min();
+                node.update();
+                return min;
+            }
+        }
+        match mem::take(self) {
+            Link::Empty => None,
+            Link::More(mut node) => {
+                *self = mem::take(&mut node.right);
+                Some(node)
+            }
+        }
+    }
+
+    fn size(&self) -> usize {
+        match *self {
+            Link::Empty => 0,
+            Link::More(ref node) => node.size
+        }
+    }
+
+    fn sum(&self) -> i64 {
+        match *self {
+            Link::Empty => 0,
+            Link::More(ref node) => node.sum
+        }
+    }
+
+    // Return the count and the sum of the elements within [lo, hi].
+    fn range_aggregate(&self, lo: i32, hi: i32) -> (usize, i64) {
+        match *self {
+            Link::Empty => (0, 0),
+            Link::More(ref node) => {
+                if node.max < lo || node.min > hi {
+                    (0, 0)
+                } else if lo <= node.min && node.max <= hi {
+                    (node.size, node.sum)
+                } else {
+                    let (left_count, left_sum) = node.left.range_aggregate(lo, hi);
+                    let (right_count, right_sum) = node.right.range_aggregate(lo, hi);
+                    let (count, sum) = (left_count + right_count, left_sum + right_sum);
+                    if lo <= node.elem && node.elem <= hi {
+                        (count + 1, sum + i64::from(node.elem))
+                    } else {
+                        (count, sum)
+                    }
+                }
+            }
+        }
+    }
+
+    // Return the shape of this subtree, and push the shape of every node in
+    // it onto `shapes` in pre-order, the order `write_dot` visits them in.
+    fn measure(&self, shapes: &mut Vec<Shape>) -> Shape {
+        match *self {
+            Link::Empty => Shape::default(),
+            Link::More(ref node) => {
+                let index = shapes.len();
+                shapes.push(Shape::default());
+                let left = node.left.measure(shapes);
+                let right = node.right.measure(shapes);
+                shapes[index] = Shape {
+                    size: 1 + left.size + right.size,
+                    height: 1 + cmp::max(left.height, right.height),
+                    balance: right.height as isize - left.height as isize
+                };
+                shapes[index]
+            }
+        }
+    }
+
+    // Draw this subtree, whose root is on `side` of its parent. Lines of
+    // the subtree start with `prefix`, and have a bar where they pass by
+    // the line to the parent.
+    fn draw_sideways(&self, prefix: &str, side: Side, out: &mut String) {
+        if let Link::More(ref node) = *self {
+            let bar = if side == Side::Left { "|   " } else { "    " };
+            node.right.draw_sideways(&format!("{}{}", prefix, bar), Side::Right, out);
+            let connector = match side {
+                Side::Root => "",
+                Side::Right => "/-- ",
+                Side::Left => "\\-- "
+            };
+            out.push_str(&format!("{}{}{}\n", prefix, connector, node.elem));
+            let bar = if side == Side::Right { "|   " } else { "    " };
+            node.left.draw_sideways(&format!("{}{}", prefix, bar), Side::Left, out);
+        }
+    }
+
+    // Write the nodes and edges of this subtree, and return the id of its
+    // root. `shapes` yields the shapes found by `measure` for the nodes
+    // still to be written. A missing child of a node with another child is
+    // drawn as a point, so it is clear which side the other child is on.
+    fn write_dot(&self, annotations: &[Annotation], shapes: &mut slice::Iter<Shape>, next_id: &mut usize,
+                 out: &mut String) -> usize {
+        let id = *next_id;
+        *next_id += 1;
+        let node = match *self {
+            Link::Empty => {
+                out.push_str(&format!("    n{} [shape=point];\n", id));
+                return id;
+            },
+            Link::More(ref node) => node
+        };
+        let shape = shapes.next().expect("no shape measured for a node");
+        let mut label = node.elem.to_string();
+        for annotation in annotations {
+            label.push_str(&match *annotation {
+                Annotation::Height => format!("\\nheight {}", shape.height),
+                Annotation::Size => format!("\\nsize {}", shape.size),
+                Annotation::Balance => format!("\\nbalance {}", shape.balance)
+            });
+        }
+        out.push_str(&format!("    n{} [label=\"{}\"];\n", id, label));
+        if let (Link::Empty, Link::Empty) = (&node.left, &node.right) {
+            return id;
+        }
+        for child in [&node.left, &node.right].iter() {
+            let child_id = child.write_dot(annotations, shapes, next_id, out);
+            out.push_str(&format!("    n{} -> n{};\n", id, child_id));
+        }
+        id
+    }
+}
+
+// A BST is serialized as the sequence of its elements in order, and is
+// rebuilt balanced when deserialized.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use std::fmt;
+
+    use serde::de::{Deserialize, Deserializer, Error, SeqAccess, Visitor};
+    use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+    use super::{BST, Link, Node};
+
+    impl Serialize for BST {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut seq = serializer.serialize_seq(Some(self.len()))?;
+            self.root.serialize_elems(&mut seq)?;
+            seq.end()
+        }
+    }
+
+    impl Link {
+        fn serialize_elems<S: SerializeSeq>(&self, seq: &mut S) -> Result<(), S::Error> {
+            if let Link::More(ref node) = *self {
+                node.left.serialize_elems(seq)?;
+                seq.serialize_element(&node.elem)?;
+                node.right.serialize_elems(seq)?;
+            }
+            Ok(())
+        }
+
+        // Build a balanced subtree out of sorted elements.
+        fn from_sorted(elems: &[i32]) -> Link {
+            if elems.is_empty() {
+                return Link::Empty;
+            }
+            let mid = elems.len() / 2;
+            let mut node = Node::new(elems[mid]);
+            node.left = Link::from_sorted(&elems[..mid]);
+            node.right = Link::from_sorted(&elems[mid + 1..]);
+            node.update();
+            Link::More(Box::new(node))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for BST {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_seq(BSTVisitor)
+        }
+    }
+
+    struct BSTVisitor;
+
+    impl<'de> Visitor<'de> for BSTVisitor {
+        type Value = BST;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a sorted sequence of unique integers")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<BST, A::Error> {
+            // Do not trust the size hint too far, it comes from the input.
+            let mut elems: Vec<i32> = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+            while let Some(elem) = seq.next_element()? {
+                match elems.last() {
+                    Some(&last) if last == elem => return Err(A::Error::custom("duplicate element")),
+                    Some(&last) if last > elem => return Err(A::Error::custom("elements are not sorted")),
+                    _ => elems.push(elem)
+                }
+            }
+            Ok(BST { root: Link::from_sorted(&elems) })
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::BST;
+    use super::{Annotation, BST};
 
     #[test]
     fn basics() {
@@ -103,4 +509,112 @@ mod test {
         // Check repeatly insertion
         assert_eq!(tree.insert(1), false);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn delete() {
+        let mut tree = BST::new();
+        for elem in &[4, 2, 6, 1, 3, 5, 7] {
+            tree.insert(*elem);
+        }
+
+        // Delete a leaf, a node with one child and a node with two children
+        assert_eq!(tree.delete(1), true);
+        assert_eq!(tree.delete(2), true);
+        assert_eq!(tree.delete(4), true);
+        assert_eq!(tree.delete(4), false);
+        assert_eq!(tree.len(), 4);
+
+        assert_eq!(tree.search(3), true);
+        assert_eq!(tree.search(4), false);
+        assert_eq!(tree.insert(4), true);
+        assert_eq!(tree.len(), 5);
+    }
+
+    #[test]
+    fn range_aggregates() {
+        let mut tree = BST::new();
+
+        // Check empty tree behaves right
+        assert_eq!(tree.range_sum(0, 10), 0);
+        assert_eq!(tree.range_count(0, 10), 0);
+
+        for elem in &[40, 20, 60, 10, 30, 50, 70] {
+            tree.insert(*elem);
+        }
+
+        assert_eq!(tree.range_sum(i32::MIN, i32::MAX), 280);
+        assert_eq!(tree.range_count(i32::MIN, i32::MAX), 7);
+        assert_eq!(tree.range_sum(20, 50), 140);
+        assert_eq!(tree.range_count(20, 50), 4);
+        assert_eq!(tree.range_sum(21, 29), 0);
+        assert_eq!(tree.range_count(71, 100), 0);
+
+        // Check the aggregates are kept through deletion
+        tree.delete(40);
+        tree.delete(10);
+        assert_eq!(tree.range_sum(20, 50), 100);
+        assert_eq!(tree.range_count(0, 100), 5);
+        assert_eq!(tree.range_sum(0, 100), 230);
+    }
+
+    #[test]
+    fn no_overflow() {
+        let mut tree = BST::new();
+        tree.insert(i32::MAX);
+        tree.insert(i32::MAX - 1);
+        assert_eq!(tree.range_sum(0, i32::MAX), 2 * i64::from(i32::MAX) - 1);
+    }
+
+    #[test]
+    fn visualization() {
+        let mut tree = BST::new();
+        assert_eq!(tree.to_ascii(), "");
+        assert_eq!(tree.stats(), Default::default());
+
+        for elem in &[4, 2, 6, 1, 3, 7] {
+            tree.insert(*elem);
+        }
+        assert_eq!(tree.to_ascii(), concat!(
+            "        /-- 7\n",
+            "    /-- 6\n",
+            "4\n",
+            "    |   /-- 3\n",
+            "    \\-- 2\n",
+            "        \\-- 1\n"));
+
+        let dot = tree.to_dot_with(&[Annotation::Size, Annotation::Balance]);
+        assert!(dot.starts_with("digraph BST {\n    n0 [label=\"4\\nsize 6\\nbalance 0\"];\n"));
+        assert!(dot.contains("[label=\"6\\nsize 2\\nbalance 1\"];"));
+        let dot = tree.to_dot_with(&[Annotation::Height]);
+        assert!(dot.contains("[label=\"4\\nheight 3\"];"));
+        assert!(dot.contains("[label=\"2\\nheight 2\"];"));
+        assert!(dot.contains("[label=\"7\\nheight 1\"];"));
+        assert_eq!(tree.to_dot().matches("shape=point").count(), 1);
+
+        let stats = tree.stats();
+        assert_eq!(stats.size, 6);
+        assert_eq!(stats.height, 3);
+        assert_eq!(stats.to_string(), "size 6, height 3, balance factors [0: 5] [1: 1]");
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        let mut tree = BST::new();
+        for elem in &[1, 2, 3, 4, 5, 6, 7] {
+            tree.insert(*elem);
+        }
+        let json = serde_json::to_string(&tree).unwrap();
+        assert_eq!(json, "[1,2,3,4,5,6,7]");
+
+        // Deserializing rebuilds a balanced tree with its aggregates
+        let tree: BST = serde_json::from_str(&json).unwrap();
+        assert_eq!(tree.stats().height, 3);
+        assert_eq!(tree.range_sum(2, 6), 20);
+        assert_eq!(tree.len(), 7);
+
+        assert!(serde_json::from_str::<BST>("[1,3,2]").is_err());
+        assert!(serde_json::from_str::<BST>("[1,1]").is_err());
+        assert_eq!(serde_json::from_str::<BST>("[]").unwrap().is_empty(), true);
+    }
+}
@@ -1,4 +1,11 @@
 #![cfg_attr(feature = "clippy", feature(plugin))]
 #![cfg_attr(feature = "clippy", plugin(clippy))]
+// The tests compare results against `true` and `false` with `assert_eq!`.
+#![cfg_attr(test, allow(clippy::bool_assert_comparison))]
+
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 
 pub mod first;
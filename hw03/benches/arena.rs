@@ -0,0 +1,108 @@
+//! Compare the arena-backed BST with the boxed one.
+//!
+//! Run with `cargo bench --bench arena`. Every allocation goes through a
+//! counting allocator, so the number of allocations needed to build each
+//! tree is reported together with the time to build and search it.
+
+extern crate hw03;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use hw03::arena::ArenaBST;
+use hw03::second::BST;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const SIZE: u64 = 1_000_000;
+
+// Spread the keys over the tree with a linear congruential generator, so
+// the unbalanced trees do not degenerate into lists.
+fn keys() -> Vec<u64> {
+    let mut state = 42u64;
+    (0..SIZE).map(|_| {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        state >> 16
+    }).collect()
+}
+
+struct Report {
+    allocations: usize,
+    build: Duration,
+    lookup: Duration,
+}
+
+fn measure<B, S>(keys: &[u64], build: B, search: S) -> Report
+    where B: Fn(&[u64]) -> Box<dyn Fn(&u64) -> bool>, S: Fn(&dyn Fn(&u64) -> bool, &[u64]) -> usize
+{
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let tree = build(keys);
+    let build_time = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    let start = Instant::now();
+    let found = search(&*tree, keys);
+    let lookup = start.elapsed();
+    assert_eq!(found, keys.len());
+
+    Report {
+        allocations,
+        build: build_time,
+        lookup
+    }
+}
+
+fn search_all(contains: &dyn Fn(&u64) -> bool, keys: &[u64]) -> usize {
+    keys.iter().filter(|key| contains(key)).count()
+}
+
+fn print(name: &str, report: &Report) {
+    println!("{:>8}: {:>9} allocations, build {:>8.1?}, {} lookups {:>8.1?}",
+             name, report.allocations, report.build, SIZE, report.lookup);
+}
+
+fn main() {
+    let keys = keys();
+
+    let boxed = measure(&keys, |keys| {
+        let mut tree = BST::new();
+        for key in keys {
+            tree.insert(*key);
+        }
+        Box::new(move |key| tree.search(key))
+    }, search_all);
+
+    let arena = measure(&keys, |keys| {
+        let mut tree = ArenaBST::new();
+        for key in keys {
+            tree.insert(*key);
+        }
+        Box::new(move |key| tree.search(key))
+    }, search_all);
+
+    print("boxed", &boxed);
+    print("arena", &arena);
+}
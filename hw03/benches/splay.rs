@@ -0,0 +1,80 @@
+//! Compare the splay tree with the plain BST on lookups.
+//!
+//! Run with `cargo bench --bench splay`. Both trees get the same keys in a
+//! random order, and then the same lookups in three patterns:
+//!
+//! - uniform: every key is as likely to be looked up;
+//! - skewed: nine lookups in ten go to a few dozen keys;
+//! - repeated: each key is looked up many times in a row.
+//!
+//! The splay tree restructures itself on every lookup, which costs about
+//! twice as much as the plain lookup when keys are uniform. With skewed
+//! lookups the hot keys stay near the root, but the occasional other
+//! lookups keep moving them down, and the plain BST, whose hot paths stay
+//! in the cache, is still a little faster. The splay tree only comes out
+//! ahead on repeated lookups, which find their key right at the root.
+
+extern crate hw03;
+
+use std::time::{Duration, Instant};
+
+use hw03::second::BST;
+use hw03::splay::SplayTree;
+
+const SIZE: u64 = 200_000;
+const LOOKUPS: usize = 2_000_000;
+
+// A linear congruential generator, so runs are repeatable.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self, bound: u64) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) % bound
+    }
+}
+
+fn shuffled_keys(random: &mut Random) -> Vec<u64> {
+    let mut keys: Vec<u64> = (0..SIZE).collect();
+    for i in (1..keys.len()).rev() {
+        let j = random.next(i as u64 + 1) as usize;
+        keys.swap(i, j);
+    }
+    keys
+}
+
+fn lookups(pattern: &str, random: &mut Random) -> Vec<u64> {
+    let hot = 32;
+    (0..LOOKUPS).map(|i| match pattern {
+        "uniform" => random.next(SIZE),
+        "skewed" if random.next(10) < 9 => random.next(hot) * (SIZE / hot),
+        "skewed" => random.next(SIZE),
+        _ => (i as u64 / 1000 * 7919) % SIZE
+    }).collect()
+}
+
+fn time<F: FnMut() -> usize>(mut run: F) -> Duration {
+    let start = Instant::now();
+    assert_eq!(run(), LOOKUPS);
+    start.elapsed()
+}
+
+fn main() {
+    let mut random = Random(42);
+    let keys = shuffled_keys(&mut random);
+
+    let mut bst = BST::new();
+    let mut splay = SplayTree::new();
+    for key in &keys {
+        bst.insert(*key);
+        splay.insert(*key);
+    }
+
+    for pattern in &["uniform", "skewed", "repeated"] {
+        let lookups = lookups(pattern, &mut random);
+        let bst_time = time(|| lookups.iter().filter(|key| bst.search(*key)).count());
+        let splay_time = time(|| lookups.iter().filter(|key| splay.search(*key)).count());
+        println!("{:>8}: {} lookups, bst {:>8.1?}, splay {:>8.1?}",
+                 pattern, LOOKUPS, bst_time, splay_time);
+    }
+}
@@ -0,0 +1,1600 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::mem;
+use std::ops::RangeBounds;
+use std::ptr;
+
+use compare::{Compare, Natural};
+use second::{above_lower, below_upper, Side};
+use second::{Difference, Intersection, SymmetricDifference, Union};
+
+/// A BST whose nodes all live in one `Vec` and link to each other by index,
+/// instead of every node being a separate `Box`. Removed nodes leave their
+/// slot on a free list, which later insertions reuse.
+///
+/// Growing the arena takes O(log n) allocations for n insertions, and
+/// neighbouring nodes tend to sit close together in memory.
+///
+/// It has the same API as `second::BST`, except for drawing the tree and
+/// its `stats`.
+#[derive(Clone)]
+pub struct ArenaBST<T, C = Natural> {
+    slots: Vec<Slot<T>>,
+    root: Link,
+    // The first free slot; every free slot links to the next one.
+    free: Link,
+    len: usize,
+    cmp: C,
+}
+
+#[derive(Clone, Debug)]
+enum Slot<T> {
+    Occupied(Node<T>),
+    Free(Link),
+}
+
+#[derive(Clone, Debug)]
+struct Node<T> {
+    elem: T,
+    left: Link,
+    right: Link,
+}
+
+type Link = Option<usize>;
+
+// Where a link is stored: at the root, or as a child of some node.
+#[derive(Clone, Copy)]
+enum Place {
+    Root,
+    Left(usize),
+    Right(usize),
+}
+
+fn node_at<T>(slots: &[Slot<T>], index: usize) -> &Node<T> {
+    match slots[index] {
+        Slot::Occupied(ref node) => node,
+        Slot::Free(_) => panic!("link to a free slot")
+    }
+}
+
+impl<T, C: Compare<T> + Default> Default for ArenaBST<T, C> {
+    fn default() -> Self {
+        ArenaBST::with_comparator(C::default())
+    }
+}
+
+impl<T, C> ArenaBST<T, C> {
+    fn node(&self, index: usize) -> &Node<T> {
+        node_at(&self.slots, index)
+    }
+
+    fn node_mut(&mut self, index: usize) -> &mut Node<T> {
+        match self.slots[index] {
+            Slot::Occupied(ref mut node) => node,
+            Slot::Free(_) => panic!("link to a free slot")
+        }
+    }
+
+    fn child(&self, index: usize, side: Side) -> Link {
+        match side {
+            Side::Left => self.node(index).left,
+            Side::Right => self.node(index).right
+        }
+    }
+
+    fn link_at(&self, place: Place) -> Link {
+        match place {
+            Place::Root => self.root,
+            Place::Left(parent) => self.node(parent).left,
+            Place::Right(parent) => self.node(parent).right
+        }
+    }
+
+    fn set_link(&mut self, place: Place, link: Link) {
+        match place {
+            Place::Root => self.root = link,
+            Place::Left(parent) => self.node_mut(parent).left = link,
+            Place::Right(parent) => self.node_mut(parent).right = link
+        }
+    }
+
+    // Store a node in a free slot, or at the end of the arena.
+    fn alloc(&mut self, node: Node<T>) -> usize {
+        match self.free {
+            Some(index) => {
+                if let Slot::Free(next) = self.slots[index] {
+                    self.free = next;
+                }
+                self.slots[index] = Slot::Occupied(node);
+                index
+            },
+            None => {
+                self.slots.push(Slot::Occupied(node));
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    // Put a slot on the free list and return the node it held.
+    fn dealloc(&mut self, index: usize) -> Node<T> {
+        match mem::replace(&mut self.slots[index], Slot::Free(self.free)) {
+            Slot::Occupied(node) => {
+                self.free = Some(index);
+                node
+            },
+            Slot::Free(_) => panic!("double free of a slot")
+        }
+    }
+
+    // Remove the node linked from `place` and return its element. A node
+    // with two children is replaced by its in-order successor.
+    fn take_at(&mut self, place: Place) -> T {
+        let index = self.link_at(place).expect("take from an empty link");
+        let (left, right) = {
+            let node = self.node(index);
+            (node.left, node.right)
+        };
+        let replacement = match (left, right) {
+            (None, link) | (link, None) => link,
+            (Some(_), Some(right)) => {
+                // Detach the in-order successor and move it in place of
+                // the removed node.
+                let mut next_place = Place::Right(index);
+                let mut next = right;
+                while let Some(left) = self.node(next).left {
+                    next_place = Place::Left(next);
+                    next = left;
+                }
+                let next_right = self.node(next).right;
+                self.set_link(next_place, next_right);
+                let (left, right) = {
+                    let node = self.node(index);
+                    (node.left, node.right)
+                };
+                let node = self.node_mut(next);
+                node.left = left;
+                node.right = right;
+                Some(next)
+            }
+        };
+        self.set_link(place, replacement);
+        self.len -= 1;
+        self.dealloc(index).elem
+    }
+
+    // Move all the elements out in order, leaving the BST empty.
+    fn drain(&mut self) -> IntoIter<T> {
+        let mut iter = IntoIter {
+            slots: mem::take(&mut self.slots),
+            stack: vec![]
+        };
+        iter.push_left_edge(self.root.take());
+        self.free = None;
+        self.len = 0;
+        iter
+    }
+
+    // Build a perfectly balanced subtree out of the first `len` elements of
+    // a sorted iterator, shaped the same way as `second::BST` does it.
+    fn build_balanced<I: Iterator<Item = T>>(&mut self, iter: &mut I, len: usize) -> Link {
+        if len == 0 {
+            return None;
+        }
+        let left_len = len / 2;
+        let left = self.build_balanced(iter, left_len);
+        let elem = iter.next()?;
+        let right = self.build_balanced(iter, len - left_len - 1);
+        self.len += 1;
+        Some(self.alloc(Node {
+            elem,
+            left,
+            right
+        }))
+    }
+
+    // Replace all the elements with `elems`, which must be sorted, as a
+    // balanced tree.
+    fn rebuild(&mut self, elems: Vec<T>) {
+        self.clear();
+        let len = elems.len();
+        self.slots.reserve(len);
+        self.root = self.build_balanced(&mut elems.into_iter(), len);
+    }
+
+    /// Return the number of elements in the BST.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return true if the BST has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Remove all the elements, keeping the memory of the arena.
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.root = None;
+        self.free = None;
+        self.len = 0;
+    }
+
+    /// Return the smallest element in the BST, or None if it is empty.
+    pub fn min(&self) -> Option<&T> {
+        let mut index = self.root?;
+        while let Some(left) = self.node(index).left {
+            index = left;
+        }
+        Some(&self.node(index).elem)
+    }
+
+    /// Return the largest element in the BST, or None if it is empty.
+    pub fn max(&self) -> Option<&T> {
+        let mut index = self.root?;
+        while let Some(right) = self.node(index).right {
+            index = right;
+        }
+        Some(&self.node(index).elem)
+    }
+}
+
+impl<T: Ord> ArenaBST<T> {
+    pub fn new() -> ArenaBST<T> {
+        ArenaBST::with_capacity(0)
+    }
+
+    /// Create an empty BST with room for `capacity` elements, so that no
+    /// allocation happens until it holds more.
+    pub fn with_capacity(capacity: usize) -> ArenaBST<T> {
+        ArenaBST::with_capacity_and_comparator(capacity, Natural)
+    }
+}
+
+impl<T, C: Compare<T>> ArenaBST<T, C> {
+    /// Create an empty BST whose elements are ordered by `cmp`.
+    pub fn with_comparator(cmp: C) -> ArenaBST<T, C> {
+        ArenaBST::with_capacity_and_comparator(0, cmp)
+    }
+
+    /// Create an empty BST ordered by `cmp`, with room for `capacity`
+    /// elements.
+    pub fn with_capacity_and_comparator(capacity: usize, cmp: C) -> ArenaBST<T, C> {
+        ArenaBST {
+            slots: Vec::with_capacity(capacity),
+            root: None,
+            free: None,
+            len: 0,
+            cmp
+        }
+    }
+
+    // Find the place of the link which holds `elem`, or of the empty link
+    // where it would be inserted.
+    fn find<Q: ?Sized>(&self, elem: &Q) -> Place where T: Borrow<Q>, C: Compare<Q> {
+        let mut place = Place::Root;
+        while let Some(index) = self.link_at(place) {
+            place = match self.cmp.compare(elem, self.node(index).elem.borrow()) {
+                Ordering::Less => Place::Left(index),
+                Ordering::Greater => Place::Right(index),
+                Ordering::Equal => break
+            };
+        }
+        place
+    }
+
+    /// Insert an element into the BST. Return true
+    /// if successful, or false if the element was already in the BST.
+    pub fn insert(&mut self, elem: T) -> bool {
+        let place = self.find(&elem);
+        if self.link_at(place).is_some() {
+            return false;
+        }
+        let index = self.alloc(Node {
+            elem,
+            left: None,
+            right: None
+        });
+        self.set_link(place, Some(index));
+        self.len += 1;
+        true
+    }
+
+    /// Search for an element in the BST. Return true
+    /// if the element was found.
+    pub fn search<Q: ?Sized>(&self, elem: &Q) -> bool where T: Borrow<Q>, C: Compare<Q> {
+        self.link_at(self.find(elem)).is_some()
+    }
+
+    /// Remove an element from the BST. Return true
+    /// if the element was in the BST.
+    pub fn remove<Q: ?Sized>(&mut self, elem: &Q) -> bool where T: Borrow<Q>, C: Compare<Q> {
+        self.take(elem).is_some()
+    }
+
+    /// Remove an element from the BST and return it, or None if the
+    /// element was not in the BST.
+    pub fn take<Q: ?Sized>(&mut self, elem: &Q) -> Option<T> where T: Borrow<Q>, C: Compare<Q> {
+        let place = self.find(elem);
+        self.link_at(place)?;
+        Some(self.take_at(place))
+    }
+
+    /// Return the largest element which is less than or equal to `elem`.
+    pub fn floor<Q: ?Sized>(&self, elem: &Q) -> Option<&T> where T: Borrow<Q>, C: Compare<Q> {
+        let mut cur = self.root;
+        let mut found = None;
+        while let Some(index) = cur {
+            let node = self.node(index);
+            match self.cmp.compare(elem, node.elem.borrow()) {
+                Ordering::Less => cur = node.left,
+                Ordering::Greater => {
+                    found = Some(&node.elem);
+                    cur = node.right;
+                },
+                Ordering::Equal => return Some(&node.elem)
+            }
+        }
+        found
+    }
+
+    /// Return the smallest element which is greater than or equal to `elem`.
+    pub fn ceiling<Q: ?Sized>(&self, elem: &Q) -> Option<&T> where T: Borrow<Q>, C: Compare<Q> {
+        let mut cur = self.root;
+        let mut found = None;
+        while let Some(index) = cur {
+            let node = self.node(index);
+            match self.cmp.compare(elem, node.elem.borrow()) {
+                Ordering::Less => {
+                    found = Some(&node.elem);
+                    cur = node.left;
+                },
+                Ordering::Greater => cur = node.right,
+                Ordering::Equal => return Some(&node.elem)
+            }
+        }
+        found
+    }
+
+    /// Return the largest element which is strictly less than `elem`.
+    /// `elem` itself does not need to be in the BST.
+    pub fn predecessor<Q: ?Sized>(&self, elem: &Q) -> Option<&T> where T: Borrow<Q>, C: Compare<Q> {
+        let mut cur = self.root;
+        let mut found = None;
+        while let Some(index) = cur {
+            let node = self.node(index);
+            if self.cmp.compare(node.elem.borrow(), elem) == Ordering::Less {
+                found = Some(&node.elem);
+                cur = node.right;
+            } else {
+                cur = node.left;
+            }
+        }
+        found
+    }
+
+    /// Return the smallest element which is strictly greater than `elem`.
+    /// `elem` itself does not need to be in the BST.
+    pub fn successor<Q: ?Sized>(&self, elem: &Q) -> Option<&T> where T: Borrow<Q>, C: Compare<Q> {
+        let mut cur = self.root;
+        let mut found = None;
+        while let Some(index) = cur {
+            let node = self.node(index);
+            if self.cmp.compare(node.elem.borrow(), elem) == Ordering::Greater {
+                found = Some(&node.elem);
+                cur = node.left;
+            } else {
+                cur = node.right;
+            }
+        }
+        found
+    }
+}
+
+// ============================================== IntoIter
+
+impl<T, C> IntoIterator for ArenaBST<T, C> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(mut self) -> Self::IntoIter {
+        self.drain()
+    }
+}
+
+pub struct IntoIter<T> {
+    slots: Vec<Slot<T>>,
+    stack: Vec<Node<T>>
+}
+
+impl<T> IntoIter<T> {
+    // Move the nodes along the left edge out of the arena.
+    fn push_left_edge(&mut self, mut link: Link) {
+        while let Some(index) = link {
+            if let Slot::Occupied(node) = mem::replace(&mut self.slots[index], Slot::Free(None)) {
+                link = node.left;
+                self.stack.push(node);
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.stack.pop().map(|node| {
+            self.push_left_edge(node.right);
+            node.elem
+        })
+    }
+}
+
+// ============================================== Iter
+
+pub struct Iter<'a, T: 'a> {
+    slots: &'a [Slot<T>],
+    stack: Vec<&'a Node<T>>
+}
+
+impl<'a, T: 'a> Iter<'a, T> {
+    fn push_left_edge(&mut self, mut link: Link) {
+        while let Some(index) = link {
+            let node = node_at(self.slots, index);
+            self.stack.push(node);
+            link = node.left;
+        }
+    }
+}
+
+impl<T, C> ArenaBST<T, C> {
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut iter = Iter {
+            slots: &self.slots,
+            stack: vec![]
+        };
+        iter.push_left_edge(self.root);
+        iter
+    }
+}
+
+impl<'a, T: 'a, C> IntoIterator for &'a ArenaBST<T, C> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T: 'a> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.stack.pop().map(|node| {
+            self.push_left_edge(node.right);
+            &node.elem
+        })
+    }
+}
+
+// ============================================== Range
+
+impl<T, C: Compare<T>> ArenaBST<T, C> {
+    /// Visit the elements within `range` in ascending order. Like `search`,
+    /// the bounds may be any borrowed form of the element type.
+    pub fn range<Q: ?Sized, R: RangeBounds<Q>>(&self, range: R) -> Range<'_, T>
+        where T: Borrow<Q>, C: Compare<Q>
+    {
+        let mut iter = Iter {
+            slots: &self.slots,
+            stack: vec![]
+        };
+
+        // Walk down to the first element within the lower bound, keeping
+        // the nodes still to be visited on the stack like `Iter` does.
+        let mut cur = self.root;
+        while let Some(index) = cur {
+            let node = self.node(index);
+            if above_lower(&self.cmp, node.elem.borrow(), range.start_bound()) {
+                iter.stack.push(node);
+                cur = node.left;
+            } else {
+                cur = node.right;
+            }
+        }
+
+        // Find the last element within the upper bound, where to stop.
+        let mut last = None;
+        let mut cur = self.root;
+        while let Some(index) = cur {
+            let node = self.node(index);
+            if below_upper(&self.cmp, node.elem.borrow(), range.end_bound()) {
+                last = Some(&node.elem);
+                cur = node.right;
+            } else {
+                cur = node.left;
+            }
+        }
+
+        let first = iter.stack.last().map(|node| &node.elem);
+        if let (Some(first), Some(end)) = (first, last) {
+            if self.cmp.compare(first, end) == Ordering::Greater {
+                last = None;
+            }
+        }
+        Range {
+            iter,
+            last
+        }
+    }
+}
+
+pub struct Range<'a, T: 'a> {
+    iter: Iter<'a, T>,
+    // The last element to yield, or None once the range is exhausted.
+    last: Option<&'a T>
+}
+
+impl<'a, T: 'a> Iterator for Range<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let last = self.last?;
+        let elem = self.iter.next()?;
+        if ptr::eq(elem, last) {
+            self.last = None;
+        }
+        Some(elem)
+    }
+}
+
+// ============================================== Traversals
+
+impl<T, C> ArenaBST<T, C> {
+    /// Visit the elements in pre-order: every node before its left subtree,
+    /// and that before its right subtree. Inserting the elements in this
+    /// order into an empty tree rebuilds the same shape.
+    pub fn iter_preorder(&self) -> Preorder<'_, T> {
+        Preorder {
+            slots: &self.slots,
+            stack: self.root.map(|root| self.node(root)).into_iter().collect()
+        }
+    }
+
+    /// Visit the elements in post-order: both subtrees of a node before the
+    /// node itself, so children always come before their parent.
+    pub fn iter_postorder(&self) -> Postorder<'_, T> {
+        Postorder {
+            slots: &self.slots,
+            stack: self.root.map(|root| (self.node(root), false)).into_iter().collect()
+        }
+    }
+
+    /// Visit the elements level by level from the root, each level from
+    /// left to right. Call `with_depth` on the iterator to get the depth of
+    /// every element too.
+    pub fn iter_level_order(&self) -> LevelOrder<'_, T> {
+        LevelOrder {
+            slots: &self.slots,
+            queue: self.root.map(|root| (0, self.node(root))).into_iter().collect()
+        }
+    }
+}
+
+pub struct Preorder<'a, T: 'a> {
+    slots: &'a [Slot<T>],
+    stack: Vec<&'a Node<T>>
+}
+
+impl<'a, T: 'a> Iterator for Preorder<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let slots = self.slots;
+        self.stack.pop().map(|node| {
+            // Push the right child first, so the left one is visited first.
+            self.stack.extend(node.right.map(|right| node_at(slots, right)));
+            self.stack.extend(node.left.map(|left| node_at(slots, left)));
+            &node.elem
+        })
+    }
+}
+
+pub struct Postorder<'a, T: 'a> {
+    slots: &'a [Slot<T>],
+    // Each node is pushed twice: first to have its children pushed above
+    // it, and then, marked as expanded, to be yielded after them.
+    stack: Vec<(&'a Node<T>, bool)>
+}
+
+impl<'a, T: 'a> Iterator for Postorder<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let slots = self.slots;
+        while let Some((node, expanded)) = self.stack.pop() {
+            if expanded {
+                return Some(&node.elem);
+            }
+            self.stack.push((node, true));
+            self.stack.extend(node.right.map(|right| (node_at(slots, right), false)));
+            self.stack.extend(node.left.map(|left| (node_at(slots, left), false)));
+        }
+        None
+    }
+}
+
+pub struct LevelOrder<'a, T: 'a> {
+    slots: &'a [Slot<T>],
+    queue: VecDeque<(usize, &'a Node<T>)>
+}
+
+impl<'a, T: 'a> LevelOrder<'a, T> {
+    /// Yield the depth of each element along with it, where the root has
+    /// depth 0.
+    pub fn with_depth(self) -> WithDepth<'a, T> {
+        WithDepth(self)
+    }
+
+    fn next_with_depth(&mut self) -> Option<(usize, &'a T)> {
+        let slots = self.slots;
+        self.queue.pop_front().map(|(depth, node)| {
+            self.queue.extend(node.left.map(|left| (depth + 1, node_at(slots, left))));
+            self.queue.extend(node.right.map(|right| (depth + 1, node_at(slots, right))));
+            (depth, &node.elem)
+        })
+    }
+}
+
+impl<'a, T: 'a> Iterator for LevelOrder<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_depth().map(|(_, elem)| elem)
+    }
+}
+
+pub struct WithDepth<'a, T: 'a>(LevelOrder<'a, T>);
+
+impl<'a, T: 'a> Iterator for WithDepth<'a, T> {
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_with_depth()
+    }
+}
+
+// ============================================== Cursor
+
+impl<T, C: Compare<T>> ArenaBST<T, C> {
+    /// Return a cursor at the smallest element, or at the ghost position
+    /// if the BST is empty.
+    pub fn cursor_front(&self) -> Cursor<'_, T, C> {
+        let mut cursor = Cursor { tree: self, path: vec![] };
+        cursor.move_next();
+        cursor
+    }
+
+    /// Return a cursor at the largest element, or at the ghost position
+    /// if the BST is empty.
+    pub fn cursor_back(&self) -> Cursor<'_, T, C> {
+        let mut cursor = Cursor { tree: self, path: vec![] };
+        cursor.move_prev();
+        cursor
+    }
+
+    /// Return a cursor at `elem`, or at the smallest element greater than
+    /// it if `elem` is not in the BST. The cursor is at the ghost position
+    /// if there is no such element.
+    pub fn cursor_at<Q: ?Sized>(&self, elem: &Q) -> Cursor<'_, T, C> where T: Borrow<Q>, C: Compare<Q> {
+        let path = self.path_to(elem);
+        Cursor { tree: self, path }
+    }
+
+    /// Like `cursor_front`, but the cursor can also edit the BST.
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T, C> {
+        let path = self.cursor_front().path;
+        CursorMut { tree: self, path }
+    }
+
+    /// Like `cursor_back`, but the cursor can also edit the BST.
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, T, C> {
+        let path = self.cursor_back().path;
+        CursorMut { tree: self, path }
+    }
+
+    /// Like `cursor_at`, but the cursor can also edit the BST.
+    pub fn cursor_at_mut<Q: ?Sized>(&mut self, elem: &Q) -> CursorMut<'_, T, C> where T: Borrow<Q>, C: Compare<Q> {
+        let path = self.path_to(elem);
+        CursorMut { tree: self, path }
+    }
+
+    // Like `ceiling`, but return the path down to the found node.
+    fn path_to<Q: ?Sized>(&self, elem: &Q) -> Vec<usize> where T: Borrow<Q>, C: Compare<Q> {
+        let mut path = vec![];
+        let mut found = 0;
+        let mut cur = self.root;
+        while let Some(index) = cur {
+            path.push(index);
+            let node = self.node(index);
+            match self.cmp.compare(elem, node.elem.borrow()) {
+                Ordering::Less => {
+                    found = path.len();
+                    cur = node.left;
+                },
+                Ordering::Greater => cur = node.right,
+                Ordering::Equal => {
+                    found = path.len();
+                    break;
+                }
+            }
+        }
+        path.truncate(found);
+        path
+    }
+}
+
+impl<T, C> ArenaBST<T, C> {
+    // Move a cursor path to the neighbour on `side`: the next element for
+    // `Right`, and the previous one for `Left`.
+    fn step(&self, path: &mut Vec<usize>, side: Side) {
+        let next = match path.last() {
+            None => self.root,
+            Some(&index) => self.child(index, side)
+        };
+        if let Some(index) = next {
+            // Go down one step toward `side`, and then as far as possible
+            // the other way. From the ghost position this starts at the root.
+            path.push(index);
+            while let Some(index) = self.child(path[path.len() - 1], side.other()) {
+                path.push(index);
+            }
+            return;
+        }
+        // Otherwise go up until the path comes from the other side of a
+        // node, which is the neighbour. Leaving the root means there is none.
+        while let Some(index) = path.pop() {
+            match path.last() {
+                Some(&parent) if self.child(parent, side.other()) == Some(index) => return,
+                Some(_) => {},
+                None => return
+            }
+        }
+    }
+}
+
+/// A position in an `ArenaBST` from which the elements around it can be
+/// visited in order, like `second::Cursor`.
+pub struct Cursor<'a, T: 'a, C: 'a = Natural> {
+    tree: &'a ArenaBST<T, C>,
+    // The indices of the nodes from the root down to the current one, or
+    // nothing at the ghost position.
+    path: Vec<usize>,
+}
+
+impl<'a, T: 'a, C: 'a> Clone for Cursor<'a, T, C> {
+    fn clone(&self) -> Self {
+        Cursor { tree: self.tree, path: self.path.clone() }
+    }
+}
+
+impl<'a, T: 'a, C: 'a> Cursor<'a, T, C> {
+    /// Return the element at the cursor, or None at the ghost position.
+    pub fn current(&self) -> Option<&'a T> {
+        let tree = self.tree;
+        self.path.last().map(|&index| &tree.node(index).elem)
+    }
+
+    /// Move to the next element in order.
+    pub fn move_next(&mut self) {
+        self.tree.step(&mut self.path, Side::Right)
+    }
+
+    /// Move to the previous element in order.
+    pub fn move_prev(&mut self) {
+        self.tree.step(&mut self.path, Side::Left)
+    }
+
+    /// Return the next element without moving the cursor.
+    pub fn peek_next(&self) -> Option<&'a T> {
+        let mut cursor = self.clone();
+        cursor.move_next();
+        cursor.current()
+    }
+
+    /// Return the previous element without moving the cursor.
+    pub fn peek_prev(&self) -> Option<&'a T> {
+        let mut cursor = self.clone();
+        cursor.move_prev();
+        cursor.current()
+    }
+}
+
+/// A cursor which can also remove the element it is at, and insert
+/// elements right next to it.
+///
+/// Node indices stay the same while other nodes are inserted or removed,
+/// so unlike `second::CursorMut` it keeps the path of nodes it is on.
+pub struct CursorMut<'a, T: 'a, C: 'a = Natural> {
+    tree: &'a mut ArenaBST<T, C>,
+    // The indices of the nodes from the root down to the current one, or
+    // nothing at the ghost position.
+    path: Vec<usize>,
+}
+
+impl<'a, T: 'a, C: Compare<T> + 'a> CursorMut<'a, T, C> {
+    // Return a read-only cursor at the same position.
+    fn as_cursor(&self) -> Cursor<'_, T, C> {
+        Cursor { tree: self.tree, path: self.path.clone() }
+    }
+
+    /// Return the element at the cursor, or None at the ghost position.
+    pub fn current(&self) -> Option<&T> {
+        self.path.last().map(|&index| &self.tree.node(index).elem)
+    }
+
+    /// Move to the next element in order.
+    pub fn move_next(&mut self) {
+        self.tree.step(&mut self.path, Side::Right)
+    }
+
+    /// Move to the previous element in order.
+    pub fn move_prev(&mut self) {
+        self.tree.step(&mut self.path, Side::Left)
+    }
+
+    /// Return the next element without moving the cursor.
+    pub fn peek_next(&self) -> Option<&T> {
+        self.as_cursor().peek_next()
+    }
+
+    /// Return the previous element without moving the cursor.
+    pub fn peek_prev(&self) -> Option<&T> {
+        self.as_cursor().peek_prev()
+    }
+
+    /// Remove the element at the cursor and return it, moving the cursor
+    /// to the next element. Return None at the ghost position.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let index = self.path.pop()?;
+        let place = match self.path.last() {
+            None => Place::Root,
+            Some(&parent) if self.tree.node(parent).left == Some(index) => Place::Left(parent),
+            Some(&parent) => Place::Right(parent)
+        };
+        let (left, right) = {
+            let node = self.tree.node(index);
+            (node.left, node.right)
+        };
+        let elem = self.tree.take_at(place);
+
+        // `take_at` moves the successor into the removed node's place if it
+        // had two children. With a right child only, the successor is the
+        // smallest element of that child's subtree, which is now in place.
+        // Without a right child, it is the nearest ancestor on the right.
+        match (left, right) {
+            (Some(_), Some(_)) => self.path.extend(self.tree.link_at(place)),
+            (None, Some(mut next)) => {
+                self.path.push(next);
+                while let Some(left) = self.tree.node(next).left {
+                    self.path.push(left);
+                    next = left;
+                }
+            },
+            (_, None) => {
+                let mut from_left = matches!(place, Place::Left(_));
+                while !from_left {
+                    let child = match self.path.pop() {
+                        None => break,
+                        Some(child) => child
+                    };
+                    from_left = self.path.last().is_some_and(|&parent| self.tree.node(parent).left == Some(child));
+                }
+            }
+        }
+        Some(elem)
+    }
+
+    /// Insert `elem` right after the element at the cursor, without moving
+    /// the cursor. At the ghost position, insert it before the smallest
+    /// element.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `elem` would not be between the element at the cursor and
+    /// the next one.
+    pub fn insert_after(&mut self, elem: T) {
+        {
+            let cursor = self.as_cursor();
+            assert!(self.in_order(cursor.current(), &elem, cursor.peek_next()),
+                    "insert_after requires the element to be in order");
+        }
+        // A new element is always added as a leaf, so the path to the
+        // current node stays the same.
+        self.tree.insert(elem);
+    }
+
+    /// Insert `elem` right before the element at the cursor, without moving
+    /// the cursor. At the ghost position, insert it after the largest
+    /// element.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `elem` would not be between the previous element and the
+    /// element at the cursor.
+    pub fn insert_before(&mut self, elem: T) {
+        {
+            let cursor = self.as_cursor();
+            assert!(self.in_order(cursor.peek_prev(), &elem, cursor.current()),
+                    "insert_before requires the element to be in order");
+        }
+        self.tree.insert(elem);
+    }
+
+    // Return true if `elem` is strictly between `prev` and `next`, where a
+    // missing neighbour is no bound.
+    fn in_order(&self, prev: Option<&T>, elem: &T, next: Option<&T>) -> bool {
+        prev.is_none_or(|prev| self.tree.cmp.compare(prev, elem) == Ordering::Less)
+            && next.is_none_or(|next| self.tree.cmp.compare(elem, next) == Ordering::Less)
+    }
+}
+
+// ============================================== Split and Join
+
+impl<T: Ord> ArenaBST<T> {
+    /// Build a balanced BST from an iterator which yields elements in
+    /// strictly ascending order. Takes O(n) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the elements are not strictly ascending.
+    pub fn from_sorted_iter<I: IntoIterator<Item = T>>(iter: I) -> ArenaBST<T> {
+        ArenaBST::from_sorted_iter_with_comparator(iter, Natural)
+    }
+}
+
+impl<T, C: Compare<T>> ArenaBST<T, C> {
+    /// Build a balanced BST ordered by `cmp` from an iterator which yields
+    /// elements in strictly ascending order by `cmp`. Takes O(n) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the elements are not strictly ascending.
+    pub fn from_sorted_iter_with_comparator<I: IntoIterator<Item = T>>(iter: I, cmp: C) -> ArenaBST<T, C> {
+        let elems: Vec<T> = iter.into_iter().collect();
+        assert!(elems.windows(2).all(|pair| cmp.compare(&pair[0], &pair[1]) == Ordering::Less),
+                "from_sorted_iter requires strictly ascending input");
+        let mut tree = ArenaBST::with_comparator(cmp);
+        tree.rebuild(elems);
+        tree
+    }
+
+    // Split a subtree into the elements less than `elem` and the elements
+    // greater than or equal to it. Only the nodes along one path are visited.
+    fn split_link(&mut self, link: Link, elem: &T) -> (Link, Link) {
+        match link {
+            None => (None, None),
+            Some(index) => {
+                let node = self.node(index);
+                if self.cmp.compare(&node.elem, elem) == Ordering::Less {
+                    let (less, rest) = self.split_link(node.right, elem);
+                    self.node_mut(index).right = less;
+                    (Some(index), rest)
+                } else {
+                    let (less, rest) = self.split_link(node.left, elem);
+                    self.node_mut(index).left = rest;
+                    (less, Some(index))
+                }
+            }
+        }
+    }
+
+    // Move the elements of a subtree out of the arena in order, freeing
+    // their slots.
+    fn take_subtree(&mut self, mut link: Link) -> Vec<T> {
+        let mut elems = vec![];
+        let mut stack = vec![];
+        loop {
+            while let Some(index) = link {
+                stack.push(index);
+                link = self.node(index).left;
+            }
+            match stack.pop() {
+                None => return elems,
+                Some(index) => {
+                    let node = self.dealloc(index);
+                    self.len -= 1;
+                    link = node.right;
+                    elems.push(node.elem);
+                }
+            }
+        }
+    }
+
+    /// Split the BST at `elem`. Afterwards `self` contains the elements
+    /// less than `elem`, and the returned BST contains the rest, rebuilt
+    /// balanced in an arena of its own. Takes time proportional to the
+    /// height of the tree plus the number of elements moved.
+    pub fn split_off(&mut self, elem: &T) -> ArenaBST<T, C> where C: Clone {
+        let root = self.root;
+        let (less, rest) = self.split_link(root, elem);
+        self.root = less;
+        let mut other = ArenaBST::with_comparator(self.cmp.clone());
+        other.rebuild(self.take_subtree(rest));
+        other
+    }
+
+    /// Concatenate `other` onto `self`. The elements of `other` move into
+    /// the arena of `self`, so this takes time proportional to the height
+    /// of `self` plus the size of `other`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if some element of `other` is not greater than all the
+    /// elements of `self`.
+    pub fn join(&mut self, other: ArenaBST<T, C>) {
+        if let (Some(max), Some(min)) = (self.max(), other.min()) {
+            assert!(self.cmp.compare(max, min) == Ordering::Less,
+                    "join requires every element of `other` to be greater");
+        }
+        let len = other.len;
+        let right = self.build_balanced(&mut other.into_iter(), len);
+
+        // Detach the largest element of `self` and make it the new root.
+        let mut place = Place::Root;
+        while let Some(next) = self.link_at(place).and_then(|index| self.node(index).right.map(|_| index)) {
+            place = Place::Right(next);
+        }
+        match self.link_at(place) {
+            None => self.root = right,
+            Some(index) => {
+                let left = self.node(index).left;
+                self.set_link(place, left);
+                let root = self.root;
+                let node = self.node_mut(index);
+                node.left = root;
+                node.right = right;
+                self.root = Some(index);
+            }
+        }
+    }
+}
+
+// ============================================== Set Operations
+
+impl<T, C: Compare<T>> ArenaBST<T, C> {
+    /// Visit the elements which are in `self` or `other` (or both), in
+    /// ascending order.
+    pub fn union<'a>(&'a self, other: &'a ArenaBST<T, C>) -> Union<'a, T, C, Iter<'a, T>> {
+        Union::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    /// Visit the elements which are both in `self` and `other`, in
+    /// ascending order.
+    pub fn intersection<'a>(&'a self, other: &'a ArenaBST<T, C>) -> Intersection<'a, T, C, Iter<'a, T>> {
+        Intersection::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    /// Visit the elements which are in `self` but not in `other`, in
+    /// ascending order.
+    pub fn difference<'a>(&'a self, other: &'a ArenaBST<T, C>) -> Difference<'a, T, C, Iter<'a, T>> {
+        Difference::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    /// Visit the elements which are in `self` or `other` but not in both,
+    /// in ascending order.
+    pub fn symmetric_difference<'a>(&'a self, other: &'a ArenaBST<T, C>)
+        -> SymmetricDifference<'a, T, C, Iter<'a, T>>
+    {
+        SymmetricDifference::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    /// Return true if every element of `self` is also in `other`.
+    /// Takes O(n + m) time.
+    pub fn is_subset(&self, other: &ArenaBST<T, C>) -> bool {
+        self.difference(other).next().is_none()
+    }
+
+    /// Return true if `self` and `other` have no elements in common.
+    /// Takes O(n + m) time.
+    pub fn is_disjoint(&self, other: &ArenaBST<T, C>) -> bool {
+        self.intersection(other).next().is_none()
+    }
+
+    /// Move all elements of `other` into `self`, leaving `other` empty.
+    /// Elements already in `self` are kept and the duplicates from `other`
+    /// are dropped. The result is rebuilt as a balanced tree in O(n + m).
+    pub fn append(&mut self, other: &mut ArenaBST<T, C>) {
+        let mut a = self.drain().peekable();
+        let mut b = other.drain().peekable();
+        let mut merged = Vec::new();
+        loop {
+            let order = match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) => self.cmp.compare(x, y),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break
+            };
+            match order {
+                Ordering::Less => merged.extend(a.next()),
+                Ordering::Greater => merged.extend(b.next()),
+                Ordering::Equal => {
+                    b.next();
+                    merged.extend(a.next());
+                }
+            }
+        }
+        self.rebuild(merged);
+    }
+}
+
+// ============================================== Mutation
+
+impl<T, C: Compare<T>> ArenaBST<T, C> {
+    /// Keep only the elements for which `f` returns true. The remaining
+    /// elements are rebuilt into a balanced tree in O(n).
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        let kept: Vec<T> = self.drain().filter(|elem| f(elem)).collect();
+        self.rebuild(kept);
+    }
+
+    /// Replace the element `old` with `new`, moving it to where `new`
+    /// belongs in the BST. Return the old element, or None if `old` was
+    /// not in the BST or another element equal to `new` was, in which case
+    /// nothing is changed.
+    pub fn replace<Q: ?Sized>(&mut self, old: &Q, new: T) -> Option<T> where T: Borrow<Q>, C: Compare<Q> {
+        let key: &Q = new.borrow();
+        if self.cmp.compare(key, old) != Ordering::Equal && self.search(key) {
+            return None;
+        }
+        let old = self.take(old)?;
+        self.insert(new);
+        Some(old)
+    }
+
+    /// Check that every element is ordered after the previous one, which
+    /// is what all the BST operations rely on. Takes O(n) time.
+    pub fn validate(&self) -> bool {
+        let mut iter = self.iter();
+        let mut prev = match iter.next() {
+            None => return true,
+            Some(elem) => elem
+        };
+        for elem in iter {
+            if self.cmp.compare(prev, elem) != Ordering::Less {
+                return false;
+            }
+            prev = elem;
+        }
+        true
+    }
+}
+
+// ============================================== Traits
+
+impl<T: fmt::Debug, C> fmt::Debug for ArenaBST<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, C> PartialEq for ArenaBST<T, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, C> Eq for ArenaBST<T, C> {}
+
+impl<T: Hash, C> Hash for ArenaBST<T, C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for elem in self {
+            elem.hash(state);
+        }
+        state.write_usize(self.len);
+    }
+}
+
+impl<T, C: Compare<T> + Default> FromIterator<T> for ArenaBST<T, C> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let mut tree = ArenaBST::with_capacity_and_comparator(iter.size_hint().0, C::default());
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<T, C: Compare<T>> Extend<T> for ArenaBST<T, C> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.insert(elem);
+        }
+    }
+}
+
+// ============================================== Serde
+
+// Serialized the same way as `second::BST`, whose checks on the input are
+// reused when deserializing.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use serde::de::{Deserialize, Deserializer};
+    use serde::ser::{Serialize, Serializer};
+
+    use compare::Compare;
+    use second::BST;
+    use super::ArenaBST;
+
+    impl<T: Serialize, C> Serialize for ArenaBST<T, C> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self)
+        }
+    }
+
+    impl<'de, T: Deserialize<'de>, C: Compare<T> + Default> Deserialize<'de> for ArenaBST<T, C> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let tree: BST<T, C> = BST::deserialize(deserializer)?;
+            let mut arena = ArenaBST::default();
+            arena.rebuild(tree.into_iter().collect());
+            Ok(arena)
+        }
+    }
+}
+
+// ============================================== Test
+
+#[cfg(test)]
+mod test {
+    use super::ArenaBST;
+    use compare::{Natural, Reverse};
+    use second::BST;
+    use std::ops::Bound;
+
+    fn tree_of(elems: &[i32]) -> ArenaBST<i32> {
+        let mut tree = ArenaBST::new();
+        for elem in elems {
+            tree.insert(*elem);
+        }
+        tree
+    }
+
+    // Build an `ArenaBST` and a `BST` with the same insertions and
+    // removals. Both remove a node the same way, so they end up with the
+    // same shape.
+    fn pair(len: i32, step: i32) -> (ArenaBST<i32>, BST<i32>) {
+        let mut arena = ArenaBST::new();
+        let mut boxed = BST::new();
+        for i in 0..len {
+            let elem = (i * step) % 211;
+            if i % 3 == 2 {
+                assert_eq!(arena.remove(&elem), boxed.remove(&elem));
+            } else {
+                assert_eq!(arena.insert(elem), boxed.insert(elem));
+            }
+        }
+        (arena, boxed)
+    }
+
+    fn elems<'a, I: Iterator<Item = &'a i32>>(iter: I) -> Vec<i32> {
+        iter.cloned().collect()
+    }
+
+    #[test]
+    fn basics() {
+        let mut tree = ArenaBST::new();
+
+        // Check empty tree behaves right
+        assert_eq!(tree.search(&1), false);
+        assert_eq!(tree.min(), None);
+
+        // Populate tree
+        assert_eq!(tree.insert(2), true);
+        assert_eq!(tree.insert(1), true);
+        assert_eq!(tree.insert(3), true);
+        assert_eq!(tree.insert(1), false);
+        assert_eq!(tree.len(), 3);
+
+        assert_eq!(tree.search(&1), true);
+        assert_eq!(tree.search(&4), false);
+        assert_eq!(tree.min(), Some(&1));
+        assert_eq!(tree.max(), Some(&3));
+        assert_eq!(tree.floor(&5), Some(&3));
+        assert_eq!(tree.ceiling(&0), Some(&1));
+    }
+
+    #[test]
+    fn remove() {
+        let mut tree = tree_of(&[4, 2, 6, 1, 3, 5, 7]);
+
+        // Remove a leaf, a node with one child and nodes with two children
+        assert_eq!(tree.remove(&1), true);
+        assert_eq!(tree.remove(&2), true);
+        assert_eq!(tree.remove(&4), true);
+        assert_eq!(tree.take(&6), Some(6));
+        assert_eq!(tree.remove(&6), false);
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![3, 5, 7]);
+        assert_eq!(tree.len(), 3);
+    }
+
+    #[test]
+    fn reuse_free_slots() {
+        let mut tree = tree_of(&[4, 2, 6]);
+        tree.remove(&2);
+        tree.remove(&6);
+
+        // New nodes go into the freed slots instead of growing the arena
+        tree.insert(1);
+        tree.insert(9);
+        assert_eq!(tree.slots.len(), 3);
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![1, 4, 9]);
+    }
+
+    #[test]
+    fn iter() {
+        let tree = tree_of(&[4, 2, 6, 1, 3, 5, 7]);
+        assert_eq!((&tree).into_iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(tree.into_iter().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn lookups_match_second() {
+        let (arena, boxed) = pair(600, 37);
+        assert_eq!(arena.len(), boxed.len());
+        assert_eq!(arena.min(), boxed.min());
+        assert_eq!(arena.max(), boxed.max());
+        for probe in -1..213 {
+            assert_eq!(arena.search(&probe), boxed.search(&probe));
+            assert_eq!(arena.floor(&probe), boxed.floor(&probe));
+            assert_eq!(arena.ceiling(&probe), boxed.ceiling(&probe));
+            assert_eq!(arena.predecessor(&probe), boxed.predecessor(&probe));
+            assert_eq!(arena.successor(&probe), boxed.successor(&probe));
+        }
+    }
+
+    #[test]
+    fn iterators_match_second() {
+        let (arena, boxed) = pair(600, 37);
+        assert_eq!(elems(arena.iter()), elems(boxed.iter()));
+        assert_eq!(elems(arena.iter_preorder()), elems(boxed.iter_preorder()));
+        assert_eq!(elems(arena.iter_postorder()), elems(boxed.iter_postorder()));
+        assert_eq!(arena.iter_level_order().with_depth().collect::<Vec<_>>(),
+                   boxed.iter_level_order().with_depth().collect::<Vec<_>>());
+        assert_eq!(arena.clone().into_iter().collect::<Vec<_>>(), boxed.clone().into_iter().collect::<Vec<_>>());
+
+        for start in (-10..220).step_by(23) {
+            for end in (-10..220).step_by(29) {
+                assert_eq!(elems(arena.range(start..end)), elems(boxed.range(start..end)));
+                assert_eq!(elems(arena.range(start..=end)), elems(boxed.range(start..=end)));
+                let bounds = (Bound::Excluded(start), Bound::Excluded(end));
+                assert_eq!(elems(arena.range(bounds)), elems(boxed.range(bounds)));
+            }
+            assert_eq!(elems(arena.range(start..)), elems(boxed.range(start..)));
+            assert_eq!(elems(arena.range(..start)), elems(boxed.range(..start)));
+        }
+    }
+
+    #[test]
+    fn cursors_match_second() {
+        let (arena, boxed) = pair(300, 37);
+
+        // Walk all the way around in both directions, through the ghost
+        let mut a = arena.cursor_front();
+        let mut b = boxed.cursor_front();
+        for _ in 0..arena.len() + 3 {
+            assert_eq!(a.current(), b.current());
+            assert_eq!(a.peek_next(), b.peek_next());
+            assert_eq!(a.peek_prev(), b.peek_prev());
+            a.move_next();
+            b.move_next();
+        }
+        let mut a = arena.cursor_back();
+        let mut b = boxed.cursor_back();
+        for _ in 0..arena.len() + 3 {
+            assert_eq!(a.current(), b.current());
+            a.move_prev();
+            b.move_prev();
+        }
+        for probe in -1..213 {
+            assert_eq!(arena.cursor_at(&probe).current(), boxed.cursor_at(&probe).current());
+            assert_eq!(arena.cursor_at(&probe).peek_prev(), boxed.cursor_at(&probe).peek_prev());
+        }
+    }
+
+    #[test]
+    fn cursor_mut_matches_second() {
+        for probe in -1..213 {
+            let (mut arena, mut boxed) = pair(300, 37);
+            {
+                let mut a = arena.cursor_at_mut(&probe);
+                let mut b = boxed.cursor_at_mut(&probe);
+
+                // Remove a few elements in a row from there, which covers
+                // nodes with no, one and two children
+                for _ in 0..3 {
+                    assert_eq!(a.remove_current(), b.remove_current());
+                    assert_eq!(a.current(), b.current());
+                    assert_eq!(a.peek_prev(), b.peek_prev());
+                }
+                a.move_prev();
+                b.move_prev();
+                assert_eq!(a.current(), b.current());
+            }
+            assert_eq!(elems(arena.iter_preorder()), elems(boxed.iter_preorder()));
+            assert_eq!(arena.len(), boxed.len());
+        }
+
+        // Insert into the gaps next to the cursor
+        let mut arena = ArenaBST::from_sorted_iter((0..20).map(|i| i * 10));
+        let mut boxed = BST::from_sorted_iter((0..20).map(|i| i * 10));
+        {
+            let mut a = arena.cursor_front_mut();
+            let mut b = boxed.cursor_front_mut();
+            for i in 0..20 {
+                a.insert_after(i * 10 + 5);
+                b.insert_after(i * 10 + 5);
+                if i > 0 {
+                    a.insert_before(i * 10 - 3);
+                    b.insert_before(i * 10 - 3);
+                }
+                assert_eq!(a.peek_next(), b.peek_next());
+                a.move_next();
+                b.move_next();
+                a.move_next();
+                b.move_next();
+                assert_eq!(a.current(), b.current());
+            }
+        }
+        assert_eq!(elems(arena.iter_preorder()), elems(boxed.iter_preorder()));
+        assert!(arena.validate());
+
+        let mut back = arena.cursor_back_mut();
+        assert_eq!(back.remove_current(), Some(195));
+        assert_eq!(back.current(), None);
+        assert_eq!(back.peek_prev(), Some(&190));
+    }
+
+    #[test]
+    #[should_panic(expected = "insert_after requires the element to be in order")]
+    fn cursor_insert_out_of_order() {
+        let mut tree = tree_of(&[1, 2, 3]);
+        tree.cursor_front_mut().insert_after(5);
+    }
+
+    #[test]
+    fn set_operations_match_second() {
+        let (a, a_boxed) = pair(300, 37);
+        let (b, b_boxed) = pair(200, 53);
+        assert_eq!(elems(a.union(&b)), elems(a_boxed.union(&b_boxed)));
+        assert_eq!(elems(a.intersection(&b)), elems(a_boxed.intersection(&b_boxed)));
+        assert_eq!(elems(a.difference(&b)), elems(a_boxed.difference(&b_boxed)));
+        assert_eq!(elems(a.symmetric_difference(&b)), elems(a_boxed.symmetric_difference(&b_boxed)));
+        assert_eq!(a.is_subset(&b), a_boxed.is_subset(&b_boxed));
+        assert_eq!(a.is_disjoint(&b), a_boxed.is_disjoint(&b_boxed));
+
+        let small: ArenaBST<i32> = a.iter().cloned().filter(|elem| elem % 2 == 0).collect();
+        assert_eq!(small.is_subset(&a), true);
+        assert_eq!(small.is_disjoint(&a.iter().cloned().filter(|elem| elem % 2 == 1).collect()), true);
+
+        let (mut a, mut a_boxed) = (a, a_boxed);
+        let (mut b, mut b_boxed) = (b, b_boxed);
+        a.append(&mut b);
+        a_boxed.append(&mut b_boxed);
+        assert_eq!(elems(a.iter_preorder()), elems(a_boxed.iter_preorder()));
+        assert_eq!(a.len(), a_boxed.len());
+        assert_eq!(b.is_empty(), true);
+        assert_eq!(b.iter().next(), None);
+    }
+
+    #[test]
+    fn split_join_match_second() {
+        let sorted = ArenaBST::from_sorted_iter(0..100);
+        assert_eq!(elems(sorted.iter_preorder()), elems(BST::from_sorted_iter(0..100).iter_preorder()));
+
+        for at in (-5..220).step_by(17) {
+            let (mut arena, mut boxed) = pair(400, 37);
+            let rest = arena.split_off(&at);
+            let boxed_rest = boxed.split_off(&at);
+            assert_eq!(elems(arena.iter()), elems(boxed.iter()));
+            assert_eq!(elems(rest.iter()), elems(boxed_rest.iter()));
+            assert_eq!(arena.len() + rest.len(), boxed.len() + boxed_rest.len());
+            assert!(arena.validate() && rest.validate());
+
+            arena.join(rest);
+            boxed.join(boxed_rest);
+            assert_eq!(elems(arena.iter()), elems(boxed.iter()));
+            assert_eq!(arena.len(), boxed.len());
+        }
+
+        // Moving elements out frees their slots for reuse
+        let mut tree = ArenaBST::from_sorted_iter(0..8);
+        tree.split_off(&4);
+        tree.extend(10..14);
+        assert_eq!(tree.slots.len(), 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "join requires every element of `other` to be greater")]
+    fn join_out_of_order() {
+        let mut tree = tree_of(&[1, 5]);
+        tree.join(tree_of(&[3]));
+    }
+
+    #[test]
+    fn mutation_matches_second() {
+        let (mut arena, mut boxed) = pair(400, 37);
+        arena.retain(|elem| elem % 3 != 0);
+        boxed.retain(|elem| elem % 3 != 0);
+        assert_eq!(elems(arena.iter_preorder()), elems(boxed.iter_preorder()));
+        assert_eq!(arena.len(), boxed.len());
+
+        for old in 0..30 {
+            assert_eq!(arena.replace(&old, old + 500), boxed.replace(&old, old + 500));
+        }
+        // Some of these collide with elements already there
+        for old in 30..60 {
+            assert_eq!(arena.replace(&old, old + 1), boxed.replace(&old, old + 1));
+        }
+        assert_eq!(arena.len(), boxed.len());
+        assert_eq!(elems(arena.iter_preorder()), elems(boxed.iter_preorder()));
+        assert_eq!(arena.validate(), true);
+    }
+
+    #[test]
+    fn comparator() {
+        let mut arena = ArenaBST::with_comparator(Reverse(Natural));
+        let mut boxed = BST::with_comparator(Reverse(Natural));
+        for elem in &[4, 2, 6, 1, 3, 5, 7] {
+            arena.insert(*elem);
+            boxed.insert(*elem);
+        }
+        assert_eq!(elems(arena.iter()), vec![7, 6, 5, 4, 3, 2, 1]);
+        let bounds = (Bound::Included(5), Bound::Excluded(2));
+        assert_eq!(elems(arena.range(bounds)), elems(boxed.range(bounds)));
+        assert_eq!(arena.floor(&0), boxed.floor(&0));
+        assert_eq!(arena.successor(&4), boxed.successor(&4));
+
+        let reversed = ArenaBST::from_sorted_iter_with_comparator(vec![3, 2, 1], Reverse(Natural));
+        assert_eq!(elems(reversed.iter()), vec![3, 2, 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "from_sorted_iter requires strictly ascending input")]
+    fn from_unsorted_iter() {
+        ArenaBST::from_sorted_iter(vec![1, 3, 2]);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let tree = tree_of(&[4, 2, 6, 1, 3, 5, 7]);
+        assert_eq!(serde_json::to_string(&tree).unwrap(), "[1,2,3,4,5,6,7]");
+
+        let tree: ArenaBST<i32> = serde_json::from_str("[1,2,3,4,5,6,7]").unwrap();
+        assert_eq!(elems(tree.iter_preorder()), vec![4, 2, 1, 3, 6, 5, 7]);
+        assert!(serde_json::from_str::<ArenaBST<i32>>("[1,3,2]").is_err());
+        assert!(serde_json::from_str::<ArenaBST<i32>>("[1,1]").is_err());
+    }
+
+    #[test]
+    fn traits() {
+        let tree: ArenaBST<i32> = vec![3, 1, 2].into_iter().collect();
+        assert_eq!(format!("{:?}", tree), "{1, 2, 3}");
+
+        // Trees are equal by their elements, not by their arenas
+        let mut other = tree_of(&[5, 1, 2, 3]);
+        other.remove(&5);
+        assert_eq!(other, tree);
+        assert_eq!(tree.clone(), tree);
+        assert_eq!(ArenaBST::<i32>::default(), ArenaBST::new());
+
+        other.extend(vec![4]);
+        assert!(other != tree);
+        other.clear();
+        assert_eq!(other.is_empty(), true);
+        assert_eq!(other.iter().next(), None);
+    }
+}
@@ -0,0 +1,53 @@
+
+use std::cmp::Ordering;
+
+/// A total order used to arrange the elements of a tree.
+///
+/// `L` and `R` may differ so that a tree of `T` can be searched with a
+/// borrowed form of `T`, e.g. a `&str` for a tree of `String`s.
+pub trait Compare<L: ?Sized, R: ?Sized = L> {
+    fn compare(&self, l: &L, r: &R) -> Ordering;
+}
+
+/// Order elements by their `Ord` implementation. This is the default
+/// comparator of every tree.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Natural;
+
+impl<T: Ord + ?Sized> Compare<T> for Natural {
+    fn compare(&self, l: &T, r: &T) -> Ordering {
+        l.cmp(r)
+    }
+}
+
+/// Order elements the opposite way of the wrapped comparator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Reverse<C>(pub C);
+
+impl<L: ?Sized, R: ?Sized, C: Compare<L, R>> Compare<L, R> for Reverse<C> {
+    fn compare(&self, l: &L, r: &R) -> Ordering {
+        self.0.compare(l, r).reverse()
+    }
+}
+
+// Any closure taking two elements can be used as a comparator.
+impl<T: ?Sized, F: Fn(&T, &T) -> Ordering> Compare<T> for F {
+    fn compare(&self, l: &T, r: &T) -> Ordering {
+        self(l, r)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+    use super::{Compare, Natural, Reverse};
+
+    #[test]
+    fn comparators() {
+        assert_eq!(Natural.compare(&1, &2), Ordering::Less);
+        assert_eq!(Reverse(Natural).compare(&1, &2), Ordering::Greater);
+
+        let by_len = |a: &&str, b: &&str| a.len().cmp(&b.len());
+        assert_eq!(by_len.compare(&"ccc", &"dd"), Ordering::Greater);
+    }
+}
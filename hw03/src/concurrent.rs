@@ -0,0 +1,528 @@
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::vec;
+
+/// An ordered set which can be shared between threads.
+///
+/// Every link of the tree has its own lock, and operations walk down the
+/// tree hand-over-hand: the lock of a child is taken before the lock of its
+/// parent is released. So operations on different parts of the tree only
+/// contend near the root. Locks are always taken from the top down, which
+/// keeps the operations free of deadlocks.
+///
+/// Operations also share a lock on the whole structure, which `snapshot`
+/// takes exclusively to see all the elements at a single point in time.
+pub struct ConcurrentBST<T> {
+    root: Slot<T>,
+    structure: RwLock<()>,
+    len: AtomicUsize,
+}
+
+struct Node<T> {
+    elem: T,
+    left: Slot<T>,
+    right: Slot<T>,
+}
+
+// A slot is shared with `Arc`, so that a thread can keep the slot it has
+// locked even after it releases the lock of the parent node.
+type Slot<T> = Arc<RwLock<Link<T>>>;
+type Link<T> = Option<Node<T>>;
+
+fn empty_slot<T>() -> Slot<T> {
+    Arc::new(RwLock::new(None))
+}
+
+impl<T: Ord> Default for ConcurrentBST<T> {
+    fn default() -> Self {
+        ConcurrentBST::new()
+    }
+}
+
+impl<T: Ord> ConcurrentBST<T> {
+    pub fn new() -> ConcurrentBST<T> {
+        ConcurrentBST {
+            root: empty_slot(),
+            structure: RwLock::new(()),
+            len: AtomicUsize::new(0)
+        }
+    }
+
+    /// Insert an element into the set. Return true
+    /// if successful, or false if the element was already in the set.
+    pub fn insert(&self, elem: T) -> bool {
+        let _shared = self.structure.read().unwrap();
+        let inserted = insert(&self.root, elem);
+        if inserted {
+            self.len.fetch_add(1, AtomicOrdering::SeqCst);
+        }
+        inserted
+    }
+
+    /// Remove an element from the set. Return true
+    /// if the element was in the set.
+    pub fn remove<Q: Ord + ?Sized>(&self, elem: &Q) -> bool where T: Borrow<Q> {
+        let _shared = self.structure.read().unwrap();
+        let removed = remove(&self.root, elem);
+        if removed {
+            self.len.fetch_sub(1, AtomicOrdering::SeqCst);
+        }
+        removed
+    }
+
+    /// Return true if the element is in the set.
+    pub fn contains<Q: Ord + ?Sized>(&self, elem: &Q) -> bool where T: Borrow<Q> {
+        let _shared = self.structure.read().unwrap();
+        contains(&self.root, elem)
+    }
+
+    /// Return the number of elements in the set.
+    pub fn len(&self) -> usize {
+        self.len.load(AtomicOrdering::SeqCst)
+    }
+
+    /// Return true if the set has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all the elements. Other operations wait while it is done.
+    pub fn clear(&self) {
+        let _exclusive = self.structure.write().unwrap();
+        let root = self.root.write().unwrap().take();
+        self.len.store(0, AtomicOrdering::SeqCst);
+        drop_link(root);
+    }
+
+    // Build the chain that inserting sorted elements one at a time makes,
+    // without taking the quadratic time to do it.
+    #[cfg(test)]
+    fn right_chain<I: IntoIterator<Item = T>>(elems: I) -> ConcurrentBST<T> {
+        let set = ConcurrentBST::new();
+        let mut slot = set.root.clone();
+        for elem in elems {
+            let node = Node { elem, left: empty_slot(), right: empty_slot() };
+            let next = node.right.clone();
+            *slot.write().unwrap() = Some(node);
+            slot = next;
+            set.len.fetch_add(1, AtomicOrdering::SeqCst);
+        }
+        set
+    }
+}
+
+impl<T: Clone> ConcurrentBST<T> {
+    /// Visit a copy of the elements in ascending order, as they are at the
+    /// time of the call. Other operations wait while the copy is made.
+    pub fn snapshot(&self) -> vec::IntoIter<T> {
+        let _exclusive = self.structure.write().unwrap();
+        let mut elems = Vec::with_capacity(self.len.load(AtomicOrdering::SeqCst));
+        collect(&self.root, &mut elems);
+        elems.into_iter()
+    }
+}
+
+// A locked slot, which holds its own reference to the slot. The guard of a
+// child then does not borrow from the parent, so walking down the tree can
+// keep it in a loop variable while the lock of the parent is released.
+struct Locked<G, T> {
+    // Declared before the slot, so it is dropped first.
+    guard: G,
+    _slot: Slot<T>,
+}
+
+impl<G: Deref, T> Deref for Locked<G, T> {
+    type Target = G::Target;
+
+    fn deref(&self) -> &G::Target {
+        &self.guard
+    }
+}
+
+impl<G: DerefMut, T> DerefMut for Locked<G, T> {
+    fn deref_mut(&mut self) -> &mut G::Target {
+        &mut self.guard
+    }
+}
+
+type WriteLocked<'a, T> = Locked<RwLockWriteGuard<'a, Link<T>>, T>;
+type ReadLocked<'a, T> = Locked<RwLockReadGuard<'a, Link<T>>, T>;
+
+// Borrow the lock inside a slot for a lifetime the caller chooses.
+//
+// # Safety
+//
+// The returned reference must not outlive a clone of `slot` which is
+// dropped after it. The lock lives in the allocation of the `Arc`, so that
+// clone keeps it in place however the clone itself is moved.
+unsafe fn lock_of<'a, T>(slot: &Slot<T>) -> &'a RwLock<Link<T>> {
+    &*Arc::as_ptr(slot)
+}
+
+fn write_lock<'a, T>(slot: &Slot<T>) -> WriteLocked<'a, T> {
+    // SAFETY: the `Locked` holds a clone of the slot, which it drops after
+    // the guard, and the guard is never handed out of it.
+    let guard = unsafe { lock_of(slot) }.write().unwrap();
+    Locked { guard, _slot: slot.clone() }
+}
+
+fn read_lock<'a, T>(slot: &Slot<T>) -> ReadLocked<'a, T> {
+    // SAFETY: as in `write_lock`.
+    let guard = unsafe { lock_of(slot) }.read().unwrap();
+    Locked { guard, _slot: slot.clone() }
+}
+
+// Each of the following functions walks down from `root` in a loop. To go
+// down a level, it locks the child's slot and then replaces the guard of
+// its own slot with it, which unlocks the parent only once the child is
+// locked.
+
+fn insert<T: Ord>(root: &Slot<T>, elem: T) -> bool {
+    let mut guard = write_lock(root);
+    loop {
+        let child = match *guard {
+            None => break,
+            Some(ref node) => match elem.cmp(&node.elem) {
+                Ordering::Less => write_lock(&node.left),
+                Ordering::Greater => write_lock(&node.right),
+                Ordering::Equal => return false
+            }
+        };
+        guard = child;
+    }
+    *guard = Some(Node {
+        elem,
+        left: empty_slot(),
+        right: empty_slot()
+    });
+    true
+}
+
+fn contains<T: Borrow<Q>, Q: Ord + ?Sized>(root: &Slot<T>, elem: &Q) -> bool {
+    let mut guard = read_lock(root);
+    loop {
+        let child = match *guard {
+            None => return false,
+            Some(ref node) => match elem.cmp(node.elem.borrow()) {
+                Ordering::Less => read_lock(&node.left),
+                Ordering::Greater => read_lock(&node.right),
+                Ordering::Equal => return true
+            }
+        };
+        guard = child;
+    }
+}
+
+fn remove<T: Borrow<Q>, Q: Ord + ?Sized>(root: &Slot<T>, elem: &Q) -> bool {
+    let mut guard = write_lock(root);
+    loop {
+        let child = match *guard {
+            None => return false,
+            Some(ref node) => match elem.cmp(node.elem.borrow()) {
+                Ordering::Less => write_lock(&node.left),
+                Ordering::Greater => write_lock(&node.right),
+                Ordering::Equal => break
+            }
+        };
+        guard = child;
+    }
+    unlink(&mut guard);
+    true
+}
+
+// Remove the node of a locked slot, putting its in-order successor (or its
+// only child) in its place. No other thread can reach the node's children
+// while its slot is locked, and the ones already inside a child's subtree
+// hold locks further down, which stay valid when the subtree is moved.
+fn unlink<T>(link: &mut Link<T>) {
+    let node = match link.take() {
+        Some(node) => node,
+        None => return
+    };
+    let next = {
+        let mut left = node.left.write().unwrap();
+        let mut right = write_lock(&node.right);
+        if left.is_none() {
+            *link = right.take();
+            return;
+        }
+        if right.is_none() {
+            *link = left.take();
+            return;
+        }
+        drop(left);
+        take_min(right)
+    };
+    *link = next.map(|elem| Node {
+        elem,
+        left: node.left,
+        right: node.right
+    });
+}
+
+// Remove the smallest element of the subtree in a locked slot, putting the
+// right subtree of its node in its place.
+fn take_min<T>(mut guard: WriteLocked<'_, T>) -> Option<T> {
+    loop {
+        let left = match *guard {
+            None => return None,
+            Some(ref node) => write_lock(&node.left)
+        };
+        if left.is_none() {
+            break;
+        }
+        guard = left;
+    }
+    let node = guard.take()?;
+    *guard = node.right.write().unwrap().take();
+    Some(node.elem)
+}
+
+// Copy the elements in order, with a stack of the slots whose elements
+// are still to come.
+fn collect<T: Clone>(root: &Slot<T>, elems: &mut Vec<T>) {
+    let mut stack = vec![];
+    let mut cur = Some(root.clone());
+    loop {
+        while let Some(slot) = cur {
+            cur = slot.read().unwrap().as_ref().map(|node| node.left.clone());
+            stack.push(slot);
+        }
+        let slot = match stack.pop() {
+            Some(slot) => slot,
+            None => return
+        };
+        let guard = slot.read().unwrap();
+        if let Some(ref node) = *guard {
+            elems.push(node.elem.clone());
+            cur = Some(node.right.clone());
+        }
+    }
+}
+
+// Build a balanced subtree out of the first `len` elements of a sorted
+// iterator, like `second` does.
+fn build_balanced<T, I: Iterator<Item = T>>(iter: &mut I, len: usize) -> Link<T> {
+    if len == 0 {
+        return None;
+    }
+    let left = build_balanced(iter, len / 2);
+    let elem = iter.next()?;
+    let right = build_balanced(iter, len - len / 2 - 1);
+    Some(Node {
+        elem,
+        left: Arc::new(RwLock::new(left)),
+        right: Arc::new(RwLock::new(right))
+    })
+}
+
+// Free the nodes of a subtree one at a time, so a deep tree does not
+// overflow the stack. No other thread can be inside the subtree.
+fn drop_link<T>(link: Link<T>) {
+    let mut stack: Vec<Node<T>> = link.into_iter().collect();
+    while let Some(node) = stack.pop() {
+        for slot in [&node.left, &node.right].iter() {
+            let mut guard = slot.write().unwrap_or_else(PoisonError::into_inner);
+            stack.extend(guard.take());
+        }
+    }
+}
+
+impl<T> Drop for ConcurrentBST<T> {
+    fn drop(&mut self) {
+        let root = self.root.write().unwrap_or_else(PoisonError::into_inner).take();
+        drop_link(root);
+    }
+}
+
+// ============================================== Traits
+
+// The traits which need to see all the elements work on a snapshot.
+
+impl<T: Ord + Clone> Clone for ConcurrentBST<T> {
+    /// Copy the elements into a balanced tree, whatever the shape of this
+    /// one.
+    fn clone(&self) -> Self {
+        let elems: Vec<T> = self.snapshot().collect();
+        let len = elems.len();
+        ConcurrentBST {
+            root: Arc::new(RwLock::new(build_balanced(&mut elems.into_iter(), len))),
+            structure: RwLock::new(()),
+            len: AtomicUsize::new(len)
+        }
+    }
+}
+
+impl<T: Clone + fmt::Debug> fmt::Debug for ConcurrentBST<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.snapshot()).finish()
+    }
+}
+
+impl<T: Clone + PartialEq> PartialEq for ConcurrentBST<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.snapshot().eq(other.snapshot())
+    }
+}
+
+impl<T: Clone + Eq> Eq for ConcurrentBST<T> {}
+
+impl<T: Clone + Hash> Hash for ConcurrentBST<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let elems = self.snapshot();
+        let len = elems.len();
+        for elem in elems {
+            elem.hash(state);
+        }
+        state.write_usize(len);
+    }
+}
+
+impl<T: Ord> FromIterator<T> for ConcurrentBST<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = ConcurrentBST::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T: Ord> Extend<T> for ConcurrentBST<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.insert(elem);
+        }
+    }
+}
+
+// ============================================== Test
+
+#[cfg(test)]
+mod test {
+    use super::ConcurrentBST;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn basics() {
+        let set = ConcurrentBST::new();
+
+        // Check empty set behaves right
+        assert_eq!(set.contains(&1), false);
+        assert_eq!(set.is_empty(), true);
+
+        // Populate set
+        assert_eq!(set.insert(2), true);
+        assert_eq!(set.insert(1), true);
+        assert_eq!(set.insert(3), true);
+        assert_eq!(set.insert(1), false);
+        assert_eq!(set.len(), 3);
+
+        assert_eq!(set.contains(&1), true);
+        assert_eq!(set.contains(&4), false);
+
+        // Remove the root, which has two children
+        assert_eq!(set.remove(&2), true);
+        assert_eq!(set.remove(&2), false);
+        assert_eq!(set.snapshot().collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn stress() {
+        const THREADS: i32 = 8;
+        const PER_THREAD: i32 = 500;
+        let set = Arc::new(ConcurrentBST::new());
+
+        // Every thread inserts its own numbers, spread over the whole tree,
+        // and then removes the even ones while others are still working.
+        let handles: Vec<_> = (0..THREADS).map(|t| {
+            let set = set.clone();
+            thread::spawn(move || {
+                for i in 0..PER_THREAD {
+                    let elem = (i * 7919) % PER_THREAD * THREADS + t;
+                    assert_eq!(set.insert(elem), true);
+                    assert_eq!(set.contains(&elem), true);
+                }
+                for elem in (t..PER_THREAD * THREADS).step_by(THREADS as usize) {
+                    if elem % 2 == 0 {
+                        assert_eq!(set.remove(&elem), true);
+                        assert_eq!(set.contains(&elem), false);
+                    }
+                }
+            })
+        }).collect();
+
+        // Snapshots taken in the middle of the updates are still sorted
+        for _ in 0..20 {
+            let elems: Vec<_> = set.snapshot().collect();
+            assert!(elems.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        let odds: Vec<_> = (0..PER_THREAD * THREADS).filter(|elem| elem % 2 == 1).collect();
+        assert_eq!(set.snapshot().collect::<Vec<_>>(), odds);
+        assert_eq!(set.len(), odds.len());
+    }
+
+    #[test]
+    fn traits() {
+        let set: ConcurrentBST<i32> = vec![3, 1, 2].into_iter().collect();
+        assert_eq!(format!("{:?}", set), "{1, 2, 3}");
+
+        let mut copy = set.clone();
+        assert_eq!(copy, set);
+        copy.extend(vec![4]);
+        assert!(copy != set);
+
+        copy.clear();
+        assert_eq!(copy.is_empty(), true);
+        assert_eq!(copy.contains(&1), false);
+        assert_eq!(copy.insert(1), true);
+    }
+
+    #[test]
+    fn ascending() {
+        // Sorted inserts make the tree a chain leaning to the right
+        let set = ConcurrentBST::new();
+        for elem in 0..2000 {
+            assert_eq!(set.insert(elem), true);
+        }
+        assert_eq!(set.contains(&1999), true);
+        assert_eq!(set.remove(&0), true);
+        assert_eq!(set.snapshot().len(), 1999);
+    }
+
+    #[test]
+    fn deep_chain() {
+        const N: i32 = 50_000;
+        let set = ConcurrentBST::right_chain(0..N);
+
+        // Every operation walks the whole chain without recursing
+        assert_eq!(set.contains(&(N - 1)), true);
+        assert_eq!(set.insert(N), true);
+        assert_eq!(set.remove(&(N - 1)), true);
+        assert_eq!(set.remove(&0), true);
+        let elems: Vec<_> = set.snapshot().collect();
+        assert_eq!(elems.len(), N as usize - 1);
+        assert_eq!(elems.last(), Some(&N));
+
+        // A copy is balanced, so it can be searched quickly
+        let copy = set.clone();
+        assert_eq!(copy, set);
+        for elem in (1..N).step_by(100) {
+            assert_eq!(copy.contains(&elem), elem != N - 1);
+        }
+        copy.clear();
+        assert_eq!(copy.is_empty(), true);
+        drop(set);
+    }
+}
@@ -0,0 +1,413 @@
+
+use std::cmp::{self, Ordering};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::{Bound, Range};
+
+use second::{unlink, Children};
+
+/// A set of half-open intervals `start..end`, kept in a BST ordered by
+/// their start (and then by their end). Every node also remembers the
+/// largest end of its subtree, so the subtrees which cannot overlap a
+/// query are skipped.
+///
+/// Empty intervals, where `start >= end`, can be stored but never overlap
+/// anything.
+#[derive(Clone)]
+pub struct IntervalTree<T: Ord + Clone> {
+    root: Link<T>,
+    len: usize,
+}
+
+#[derive(Clone, Debug)]
+struct Node<T: Ord + Clone> {
+    interval: Range<T>,
+    max_end: T,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+type Link<T> = Option<Box<Node<T>>>;
+
+impl<T: Ord + Clone> Default for IntervalTree<T> {
+    fn default() -> Self {
+        IntervalTree::new()
+    }
+}
+
+impl<T: Ord + Clone> IntervalTree<T> {
+    pub fn new() -> IntervalTree<T> {
+        IntervalTree { root: None, len: 0 }
+    }
+
+    /// Insert an interval into the tree. Return true
+    /// if successful, or false if the interval was already in the tree.
+    pub fn insert(&mut self, interval: Range<T>) -> bool {
+        let inserted = insert(&mut self.root, interval);
+        if inserted {
+            self.len += 1;
+        }
+        inserted
+    }
+
+    /// Remove an interval from the tree. Return true
+    /// if the interval was in the tree.
+    pub fn remove(&mut self, interval: &Range<T>) -> bool {
+        let removed = remove(&mut self.root, interval);
+        if removed {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    /// Return the number of intervals in the tree.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return true if the tree has no intervals.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Remove all the intervals.
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+
+    /// Search for an interval in the tree. Return true
+    /// if the interval was found.
+    pub fn search(&self, interval: &Range<T>) -> bool {
+        let mut cur = self.root.as_ref();
+        while let Some(node) = cur {
+            match compare(interval, &node.interval) {
+                Ordering::Less => cur = node.left.as_ref(),
+                Ordering::Greater => cur = node.right.as_ref(),
+                Ordering::Equal => return true
+            }
+        }
+        false
+    }
+
+    /// Visit the intervals which contain `point`, ordered by their start.
+    pub fn stab(&self, point: &T) -> Overlaps<'_, T> {
+        Overlaps::new(self.root.as_deref(), point.clone(), Bound::Included(point.clone()))
+    }
+
+    /// Visit the intervals which overlap `range`, ordered by their start.
+    /// An empty `range` overlaps nothing.
+    pub fn overlapping(&self, range: &Range<T>) -> Overlaps<'_, T> {
+        let root = if range.start < range.end { self.root.as_deref() } else { None };
+        Overlaps::new(root, range.start.clone(), Bound::Excluded(range.end.clone()))
+    }
+
+    /// Visit all the intervals, ordered by their start.
+    pub fn iter(&self) -> Overlaps<'_, T> {
+        let mut iter = Overlaps {
+            stack: vec![],
+            lo: None,
+            hi: Bound::Unbounded
+        };
+        iter.push_left_edge(self.root.as_deref());
+        iter
+    }
+}
+
+// Order intervals by their start, then by their end.
+fn compare<T: Ord>(a: &Range<T>, b: &Range<T>) -> Ordering {
+    a.start.cmp(&b.start).then_with(|| a.end.cmp(&b.end))
+}
+
+impl<T: Ord + Clone> Node<T> {
+    fn new(interval: Range<T>) -> Node<T> {
+        Node {
+            max_end: interval.end.clone(),
+            interval,
+            left: None,
+            right: None
+        }
+    }
+}
+
+// The nodes are removed with the helpers of `second`, which keep `max_end`
+// up to date through `update`.
+impl<T: Ord + Clone> Children for Node<T> {
+    fn left(&mut self) -> &mut Link<T> {
+        &mut self.left
+    }
+
+    fn right(&mut self) -> &mut Link<T> {
+        &mut self.right
+    }
+
+    // Recompute `max_end` from the children, which must be up to date.
+    fn update(&mut self) {
+        let mut max_end = &self.interval.end;
+        for child in [&self.left, &self.right].iter() {
+            if let Some(ref child) = **child {
+                max_end = cmp::max(max_end, &child.max_end);
+            }
+        }
+        self.max_end = max_end.clone();
+    }
+}
+
+fn insert<T: Ord + Clone>(link: &mut Link<T>, interval: Range<T>) -> bool {
+    match *link {
+        None => {
+            *link = Some(Box::new(Node::new(interval)));
+            true
+        },
+        Some(ref mut node) => {
+            let inserted = match compare(&interval, &node.interval) {
+                Ordering::Less => insert(&mut node.left, interval),
+                Ordering::Greater => insert(&mut node.right, interval),
+                Ordering::Equal => false
+            };
+            if inserted {
+                node.update();
+            }
+            inserted
+        }
+    }
+}
+
+fn remove<T: Ord + Clone>(link: &mut Link<T>, interval: &Range<T>) -> bool {
+    let order = match *link {
+        None => return false,
+        Some(ref node) => compare(interval, &node.interval)
+    };
+    let node = match order {
+        Ordering::Equal => {
+            unlink(link);
+            return true;
+        },
+        _ => link.as_mut().unwrap()
+    };
+    let removed = if order == Ordering::Less {
+        remove(&mut node.left, interval)
+    } else {
+        remove(&mut node.right, interval)
+    };
+    if removed {
+        node.update();
+    }
+    removed
+}
+
+// ============================================== Overlaps
+
+/// An iterator over the intervals which overlap a query, in order.
+pub struct Overlaps<'a, T: Ord + Clone + 'a> {
+    stack: Vec<&'a Node<T>>,
+    // An interval overlaps the query if it ends after `lo` and starts
+    // within `hi`. `lo` is None when every interval is wanted.
+    lo: Option<T>,
+    hi: Bound<T>
+}
+
+impl<'a, T: Ord + Clone + 'a> Overlaps<'a, T> {
+    fn new(root: Option<&'a Node<T>>, lo: T, hi: Bound<T>) -> Overlaps<'a, T> {
+        let mut iter = Overlaps {
+            stack: vec![],
+            lo: Some(lo),
+            hi
+        };
+        iter.push_left_edge(root);
+        iter
+    }
+
+    fn ends_after_lo(&self, end: &T) -> bool {
+        match self.lo {
+            Some(ref lo) => end > lo,
+            None => true
+        }
+    }
+
+    fn starts_before_hi(&self, start: &T) -> bool {
+        match self.hi {
+            Bound::Included(ref hi) => start <= hi,
+            Bound::Excluded(ref hi) => start < hi,
+            Bound::Unbounded => true
+        }
+    }
+
+    // Like `Iter` of `second`, push the node and its left descendants, but
+    // stop at subtrees which end before the query starts.
+    fn push_left_edge(&mut self, mut link: Option<&'a Node<T>>) {
+        while let Some(node) = link {
+            if !self.ends_after_lo(&node.max_end) {
+                break;
+            }
+            self.stack.push(node);
+            link = node.left.as_deref();
+        }
+    }
+}
+
+impl<'a, T: Ord + Clone + 'a> Iterator for Overlaps<'a, T> {
+    type Item = &'a Range<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.stack.pop() {
+            // Nodes are visited by increasing start, so once one starts
+            // after the query, so does everything else.
+            if !self.starts_before_hi(&node.interval.start) {
+                self.stack.clear();
+                return None;
+            }
+            self.push_left_edge(node.right.as_deref());
+            if self.ends_after_lo(&node.interval.end) {
+                return Some(&node.interval);
+            }
+        }
+        None
+    }
+}
+
+// ============================================== Traits
+
+impl<T: Ord + Clone + fmt::Debug> fmt::Debug for IntervalTree<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Ord + Clone> PartialEq for IntervalTree<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Ord + Clone> Eq for IntervalTree<T> {}
+
+impl<T: Ord + Clone + Hash> Hash for IntervalTree<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for interval in self.iter() {
+            interval.hash(state);
+        }
+        state.write_usize(self.len);
+    }
+}
+
+impl<T: Ord + Clone> FromIterator<Range<T>> for IntervalTree<T> {
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(iter: I) -> Self {
+        let mut tree = IntervalTree::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<T: Ord + Clone> Extend<Range<T>> for IntervalTree<T> {
+    fn extend<I: IntoIterator<Item = Range<T>>>(&mut self, iter: I) {
+        for interval in iter {
+            self.insert(interval);
+        }
+    }
+}
+
+// ============================================== Test
+
+#[cfg(test)]
+mod test {
+    use super::{IntervalTree, Link};
+
+    // Check every node's `max_end` against its subtree.
+    fn check_max_end(link: &Link<i32>) -> Option<i32> {
+        link.as_ref().map(|node| {
+            let mut max_end = node.interval.end;
+            for child in [check_max_end(&node.left), check_max_end(&node.right)].iter() {
+                if let Some(end) = *child {
+                    max_end = max_end.max(end);
+                }
+            }
+            assert_eq!(node.max_end, max_end);
+            max_end
+        })
+    }
+
+    fn windows() -> IntervalTree<i32> {
+        vec![15..20, 10..30, 17..19, 5..20, 12..15, 30..40].into_iter().collect()
+    }
+
+    #[test]
+    fn basics() {
+        let mut tree = IntervalTree::new();
+
+        // Check empty tree behaves right
+        assert_eq!(tree.search(&(1..2)), false);
+        assert_eq!(tree.stab(&1).count(), 0);
+
+        // Populate tree
+        assert_eq!(tree.insert(1..5), true);
+        assert_eq!(tree.insert(1..3), true);
+        assert_eq!(tree.insert(2..8), true);
+        assert_eq!(tree.insert(1..5), false);
+        check_max_end(&tree.root);
+
+        assert_eq!(tree.search(&(1..3)), true);
+        assert_eq!(tree.search(&(1..4)), false);
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![1..3, 1..5, 2..8]);
+    }
+
+    #[test]
+    fn stab() {
+        let tree = windows();
+
+        assert_eq!(tree.stab(&18).cloned().collect::<Vec<_>>(), vec![5..20, 10..30, 15..20, 17..19]);
+        assert_eq!(tree.stab(&30).cloned().collect::<Vec<_>>(), vec![30..40]);
+        assert_eq!(tree.stab(&4).count(), 0);
+        assert_eq!(tree.stab(&40).count(), 0);
+    }
+
+    #[test]
+    fn overlapping() {
+        let tree = windows();
+
+        assert_eq!(tree.overlapping(&(0..6)).cloned().collect::<Vec<_>>(), vec![5..20]);
+        assert_eq!(tree.overlapping(&(19..31)).cloned().collect::<Vec<_>>(), vec![5..20, 10..30, 15..20, 30..40]);
+        assert_eq!(tree.overlapping(&(0..100)).count(), 6);
+        assert_eq!(tree.overlapping(&(40..50)).count(), 0);
+
+        // Empty ranges overlap nothing, not even the intervals around them
+        assert_eq!(tree.overlapping(&(18..18)).count(), 0);
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = 20..10;
+        assert_eq!(tree.overlapping(&reversed).count(), 0);
+    }
+
+    #[test]
+    fn remove() {
+        let mut tree = windows();
+
+        assert_eq!(tree.remove(&(10..30)), true);
+        assert_eq!(tree.remove(&(10..30)), false);
+        check_max_end(&tree.root);
+        assert_eq!(tree.stab(&25).count(), 0);
+
+        assert_eq!(tree.remove(&(15..20)), true);
+        assert_eq!(tree.remove(&(5..20)), true);
+        check_max_end(&tree.root);
+        assert_eq!(tree.stab(&18).cloned().collect::<Vec<_>>(), vec![17..19]);
+        assert_eq!(tree.iter().count(), 3);
+    }
+
+    #[test]
+    fn traits() {
+        let mut tree = windows();
+        assert_eq!(tree.len(), 6);
+        assert_eq!(format!("{:?}", tree), "{5..20, 10..30, 12..15, 15..20, 17..19, 30..40}");
+
+        let copy = tree.clone();
+        assert_eq!(copy, tree);
+        tree.extend(vec![1..2, 5..20]);
+        assert_eq!(tree.len(), 7);
+        assert!(copy != tree);
+
+        tree.clear();
+        assert_eq!(tree.is_empty(), true);
+        assert_eq!(tree.stab(&18).count(), 0);
+    }
+}
@@ -1,4 +1,19 @@
 #![cfg_attr(feature="clippy", feature(plugin))]
 #![cfg_attr(feature="clippy", plugin(clippy))]
+// The tests compare results against `true` and `false` with `assert_eq!`.
+#![cfg_attr(test, allow(clippy::bool_assert_comparison))]
 
-pub mod second;
\ No newline at end of file
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+
+pub mod arena;
+pub mod compare;
+pub mod concurrent;
+pub mod interval;
+pub mod multiset;
+pub mod persistent;
+pub mod second;
+pub mod splay;
+pub mod treap;
\ No newline at end of file
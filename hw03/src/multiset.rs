@@ -0,0 +1,264 @@
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+
+use compare::{Compare, Natural};
+use second::{BSTMap, MapIter};
+
+/// A BST which keeps duplicate elements. Each distinct element is stored
+/// once in a node together with the number of times it was inserted.
+#[derive(Clone)]
+pub struct MultiSet<T, C = Natural> {
+    counts: BSTMap<T, usize, C>,
+    len: usize,
+}
+
+impl<T, C: Compare<T> + Default> Default for MultiSet<T, C> {
+    fn default() -> Self {
+        MultiSet::with_comparator(C::default())
+    }
+}
+
+impl<T: Ord> MultiSet<T> {
+    pub fn new() -> MultiSet<T> {
+        MultiSet::with_comparator(Natural)
+    }
+}
+
+impl<T, C: Compare<T>> MultiSet<T, C> {
+    /// Create an empty multiset whose elements are ordered by `cmp`.
+    pub fn with_comparator(cmp: C) -> MultiSet<T, C> {
+        MultiSet {
+            counts: BSTMap::with_comparator(cmp),
+            len: 0
+        }
+    }
+
+    /// Insert an element into the multiset. Return the number of times
+    /// the element is in the multiset afterwards.
+    pub fn insert(&mut self, elem: T) -> usize {
+        self.len += 1;
+        if let Some(count) = self.counts.get_mut(&elem) {
+            *count += 1;
+            return *count;
+        }
+        self.counts.insert(elem, 1);
+        1
+    }
+
+    /// Return the number of times an element is in the multiset.
+    pub fn count<Q: ?Sized>(&self, elem: &Q) -> usize where T: Borrow<Q>, C: Compare<Q> {
+        self.counts.get(elem).cloned().unwrap_or(0)
+    }
+
+    /// Search for an element in the multiset. Return true
+    /// if the element was found.
+    pub fn search<Q: ?Sized>(&self, elem: &Q) -> bool where T: Borrow<Q>, C: Compare<Q> {
+        self.counts.contains_key(elem)
+    }
+
+    /// Remove one copy of an element. Return true
+    /// if the element was in the multiset.
+    pub fn remove_one<Q: ?Sized>(&mut self, elem: &Q) -> bool where T: Borrow<Q>, C: Compare<Q> {
+        let left = match self.counts.get_mut(elem) {
+            None => return false,
+            Some(count) => {
+                *count -= 1;
+                *count
+            }
+        };
+        if left == 0 {
+            self.counts.remove(elem);
+        }
+        self.len -= 1;
+        true
+    }
+
+    /// Remove every copy of an element. Return the number of copies
+    /// which were removed.
+    pub fn remove_all<Q: ?Sized>(&mut self, elem: &Q) -> usize where T: Borrow<Q>, C: Compare<Q> {
+        let removed = self.counts.remove(elem).unwrap_or(0);
+        self.len -= removed;
+        removed
+    }
+
+    /// Return the number of elements, counting every copy.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return true if the multiset has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Remove all the elements.
+    pub fn clear(&mut self) {
+        self.counts.clear();
+        self.len = 0;
+    }
+
+    /// Visit every copy of the elements in ascending order, so an element
+    /// inserted three times is yielded three times.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            counts: self.counts.iter(),
+            current: None
+        }
+    }
+
+    /// Visit each distinct element once in ascending order, together with
+    /// the number of times it is in the multiset.
+    pub fn iter_counts(&self) -> Counts<'_, T> {
+        Counts(self.counts.iter())
+    }
+}
+
+// ============================================== Iter
+
+pub struct Iter<'a, T: 'a> {
+    counts: MapIter<'a, T, usize>,
+    // The element being repeated and how many copies are still to come.
+    current: Option<(&'a T, usize)>
+}
+
+impl<'a, T: 'a> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((elem, ref mut left)) = self.current {
+                if *left > 0 {
+                    *left -= 1;
+                    return Some(elem);
+                }
+            }
+            self.current = Some(self.counts.next().map(|(elem, count)| (elem, *count))?);
+        }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T>> IntoIterator for &'a MultiSet<T, C> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// ============================================== Counts
+
+pub struct Counts<'a, T: 'a>(MapIter<'a, T, usize>);
+
+impl<'a, T: 'a> Iterator for Counts<'a, T> {
+    type Item = (&'a T, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(elem, count)| (elem, *count))
+    }
+}
+
+// ============================================== Traits
+
+impl<T: fmt::Debug, C: Compare<T>> fmt::Debug for MultiSet<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, C> PartialEq for MultiSet<T, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.counts == other.counts
+    }
+}
+
+impl<T: Eq, C> Eq for MultiSet<T, C> {}
+
+impl<T: Hash, C> Hash for MultiSet<T, C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.counts.hash(state);
+    }
+}
+
+impl<T, C: Compare<T> + Default> FromIterator<T> for MultiSet<T, C> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = MultiSet::default();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T, C: Compare<T>> Extend<T> for MultiSet<T, C> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.insert(elem);
+        }
+    }
+}
+
+// ============================================== Test
+
+#[cfg(test)]
+mod test {
+    use super::MultiSet;
+
+    #[test]
+    fn basics() {
+        let mut set = MultiSet::new();
+
+        // Check empty multiset behaves right
+        assert_eq!(set.count(&1), 0);
+        assert_eq!(set.is_empty(), true);
+
+        // Populate multiset with duplicates
+        assert_eq!(set.insert(1), 1);
+        assert_eq!(set.insert(2), 1);
+        assert_eq!(set.insert(1), 2);
+        assert_eq!(set.insert(1), 3);
+
+        assert_eq!(set.count(&1), 3);
+        assert_eq!(set.count(&2), 1);
+        assert_eq!(set.len(), 4);
+        assert_eq!(set.search(&2), true);
+
+        // Check removal
+        assert_eq!(set.remove_one(&1), true);
+        assert_eq!(set.count(&1), 2);
+        assert_eq!(set.remove_one(&2), true);
+        assert_eq!(set.search(&2), false);
+        assert_eq!(set.remove_one(&2), false);
+        assert_eq!(set.remove_all(&1), 2);
+        assert_eq!(set.remove_all(&1), 0);
+        assert_eq!(set.is_empty(), true);
+    }
+
+    #[test]
+    fn iter() {
+        let mut set = MultiSet::new();
+        for elem in &[3, 1, 3, 2, 3, 1] {
+            set.insert(*elem);
+        }
+
+        assert_eq!(set.iter().cloned().collect::<Vec<_>>(), vec![1, 1, 2, 3, 3, 3]);
+        assert_eq!(set.iter_counts().map(|(e, c)| (*e, c)).collect::<Vec<_>>(),
+                   vec![(1, 2), (2, 1), (3, 3)]);
+    }
+
+    #[test]
+    fn traits() {
+        let set: MultiSet<i32> = vec![3, 1, 3].into_iter().collect();
+        assert_eq!(format!("{:?}", set), "{1, 3, 3}");
+
+        let mut copy = set.clone();
+        assert_eq!(copy == set, true);
+        copy.extend(vec![1]);
+        assert_eq!(copy == set, false);
+        assert_eq!(copy.len(), 4);
+
+        copy.clear();
+        assert_eq!(copy.is_empty(), true);
+        assert_eq!(copy.count(&3), 0);
+    }
+}
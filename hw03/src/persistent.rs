@@ -0,0 +1,516 @@
+
+use std::borrow::Borrow;
+use std::cmp::{self, Ordering};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// The kind of reference-counted pointer which links the nodes of a
+/// persistent tree together.
+pub trait PointerKind {
+    type Pointer<U>: Clone + Deref<Target = U>;
+    fn new<U>(value: U) -> Self::Pointer<U>;
+
+    /// Return the value if `pointer` is the last one to it, or None.
+    fn try_unwrap<U>(pointer: Self::Pointer<U>) -> Option<U>;
+}
+
+/// Link nodes with `Rc`, which is cheap but keeps the tree on one thread.
+#[derive(Debug)]
+pub enum RcKind {}
+
+impl PointerKind for RcKind {
+    type Pointer<U> = Rc<U>;
+    fn new<U>(value: U) -> Rc<U> {
+        Rc::new(value)
+    }
+
+    fn try_unwrap<U>(pointer: Rc<U>) -> Option<U> {
+        Rc::try_unwrap(pointer).ok()
+    }
+}
+
+/// Link nodes with `Arc`, so snapshots can be shared across threads.
+#[derive(Debug)]
+pub enum ArcKind {}
+
+impl PointerKind for ArcKind {
+    type Pointer<U> = Arc<U>;
+    fn new<U>(value: U) -> Arc<U> {
+        Arc::new(value)
+    }
+
+    fn try_unwrap<U>(pointer: Arc<U>) -> Option<U> {
+        Arc::try_unwrap(pointer).ok()
+    }
+}
+
+/// An immutable BST. Inserting or removing returns a new tree which shares
+/// every node off the changed path with the old one, so both versions stay
+/// usable and keeping a snapshot only costs a `clone` of the root.
+///
+/// The tree is kept balanced like an AVL tree: the heights of the two
+/// subtrees of every node differ by at most one, so the changed path, and
+/// the cost of a new version, is O(log n) whatever the order of updates.
+pub struct PersistentBST<T, P: PointerKind = RcKind> {
+    root: Link<T, P>,
+    len: usize,
+}
+
+/// A persistent BST which is `Send` and `Sync` when `T` is.
+pub type ArcBST<T> = PersistentBST<T, ArcKind>;
+
+struct Node<T, P: PointerKind> {
+    elem: T,
+    // The number of nodes on the longest path down from this one.
+    height: usize,
+    left: Link<T, P>,
+    right: Link<T, P>,
+}
+
+type Link<T, P> = Option<<P as PointerKind>::Pointer<Node<T, P>>>;
+
+impl<T, P: PointerKind> Clone for PersistentBST<T, P> {
+    fn clone(&self) -> Self {
+        PersistentBST { root: self.root.clone(), len: self.len }
+    }
+}
+
+impl<T: Ord + Clone, P: PointerKind> Default for PersistentBST<T, P> {
+    fn default() -> Self {
+        PersistentBST::new()
+    }
+}
+
+impl<T: Ord + Clone, P: PointerKind> PersistentBST<T, P> {
+    pub fn new() -> Self {
+        PersistentBST { root: None, len: 0 }
+    }
+
+    /// Return a tree with `elem` inserted. Only the nodes on the path to
+    /// the new element are copied, which takes O(log n) time. If the
+    /// element was already in the tree, the returned tree shares all of its
+    /// nodes with `self`.
+    pub fn insert(&self, elem: T) -> Self {
+        match insert::<T, P>(&self.root, elem) {
+            Some(root) => PersistentBST { root, len: self.len + 1 },
+            None => self.clone()
+        }
+    }
+
+    /// Return a tree with `elem` removed. Only the nodes on the path to
+    /// the removed element are copied, which takes O(log n) time.
+    pub fn remove<Q: Ord + ?Sized>(&self, elem: &Q) -> Self where T: Borrow<Q> {
+        match remove::<T, P, Q>(&self.root, elem) {
+            Some(root) => PersistentBST { root, len: self.len - 1 },
+            None => self.clone()
+        }
+    }
+
+    /// Search for an element in the tree. Return true
+    /// if the element was found.
+    pub fn search<Q: Ord + ?Sized>(&self, elem: &Q) -> bool where T: Borrow<Q> {
+        let mut cur = self.root.as_ref();
+        while let Some(node) = cur {
+            match elem.cmp(node.elem.borrow()) {
+                Ordering::Less => cur = node.left.as_ref(),
+                Ordering::Greater => cur = node.right.as_ref(),
+                Ordering::Equal => return true
+            }
+        }
+        false
+    }
+
+    /// Return the number of elements in the tree.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return true if the tree has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Make this version of the tree empty. Other versions keep their
+    /// elements.
+    pub fn clear(&mut self) {
+        drop_link::<T, P>(self.root.take());
+        self.len = 0;
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Side {
+    Left,
+    Right,
+}
+
+// The nodes from the root down to a place in the tree, with the side taken
+// at each of them.
+type Path<'a, T, P> = Vec<(&'a Node<T, P>, Side)>;
+
+fn height<T, P: PointerKind>(link: &Link<T, P>) -> usize {
+    link.as_ref().map_or(0, |node| node.height)
+}
+
+fn new_node<T, P: PointerKind>(elem: T, left: Link<T, P>, right: Link<T, P>) -> Link<T, P> {
+    Some(P::new(Node {
+        elem,
+        height: 1 + cmp::max(height::<T, P>(&left), height::<T, P>(&right)),
+        left,
+        right
+    }))
+}
+
+// Make a node out of an element and two subtrees whose heights differ by
+// at most two, rotating the copies of the nodes on the higher side to
+// bring the difference back to at most one.
+fn balance<T: Clone, P: PointerKind>(elem: T, left: Link<T, P>, right: Link<T, P>) -> Link<T, P> {
+    let (left_height, right_height) = (height::<T, P>(&left), height::<T, P>(&right));
+    if left_height > right_height + 1 {
+        let l = left.as_ref().unwrap();
+        if height::<T, P>(&l.left) >= height::<T, P>(&l.right) {
+            let right = new_node::<T, P>(elem, l.right.clone(), right);
+            new_node::<T, P>(l.elem.clone(), l.left.clone(), right)
+        } else {
+            let lr = l.right.as_ref().unwrap();
+            let left = new_node::<T, P>(l.elem.clone(), l.left.clone(), lr.left.clone());
+            let right = new_node::<T, P>(elem, lr.right.clone(), right);
+            new_node::<T, P>(lr.elem.clone(), left, right)
+        }
+    } else if right_height > left_height + 1 {
+        let r = right.as_ref().unwrap();
+        if height::<T, P>(&r.right) >= height::<T, P>(&r.left) {
+            let left = new_node::<T, P>(elem, left, r.left.clone());
+            new_node::<T, P>(r.elem.clone(), left, r.right.clone())
+        } else {
+            let rl = r.left.as_ref().unwrap();
+            let left = new_node::<T, P>(elem, left, rl.left.clone());
+            let right = new_node::<T, P>(r.elem.clone(), rl.right.clone(), r.right.clone());
+            new_node::<T, P>(rl.elem.clone(), left, right)
+        }
+    } else {
+        new_node::<T, P>(elem, left, right)
+    }
+}
+
+// Copy the nodes of `path` from the bottom up, replacing the subtree at its
+// end with `link` and balancing every copy.
+fn rebuild<T: Clone, P: PointerKind>(path: Path<'_, T, P>, mut link: Link<T, P>) -> Link<T, P> {
+    for (node, side) in path.into_iter().rev() {
+        link = match side {
+            Side::Left => balance::<T, P>(node.elem.clone(), link, node.right.clone()),
+            Side::Right => balance::<T, P>(node.elem.clone(), node.left.clone(), link)
+        };
+    }
+    link
+}
+
+// Return the new root of the subtree with `elem` inserted, or None if the
+// element was already there and nothing needs to change.
+fn insert<T: Ord + Clone, P: PointerKind>(link: &Link<T, P>, elem: T) -> Option<Link<T, P>> {
+    let mut path = vec![];
+    let mut cur = link.as_ref();
+    while let Some(node) = cur {
+        match elem.cmp(&node.elem) {
+            Ordering::Less => {
+                path.push((&**node, Side::Left));
+                cur = node.left.as_ref();
+            },
+            Ordering::Greater => {
+                path.push((&**node, Side::Right));
+                cur = node.right.as_ref();
+            },
+            Ordering::Equal => return None
+        }
+    }
+    Some(rebuild(path, new_node::<T, P>(elem, None, None)))
+}
+
+// Return the new root of the subtree with `elem` removed, or None if the
+// element was not there and nothing needs to change.
+fn remove<T, P, Q>(link: &Link<T, P>, elem: &Q) -> Option<Link<T, P>>
+    where T: Ord + Clone + Borrow<Q>, P: PointerKind, Q: Ord + ?Sized
+{
+    let mut path = vec![];
+    let mut cur = link.as_ref();
+    let node = loop {
+        let node = cur?;
+        match elem.cmp(node.elem.borrow()) {
+            Ordering::Less => {
+                path.push((&**node, Side::Left));
+                cur = node.left.as_ref();
+            },
+            Ordering::Greater => {
+                path.push((&**node, Side::Right));
+                cur = node.right.as_ref();
+            },
+            Ordering::Equal => break node
+        }
+    };
+    let replacement = match (&node.left, &node.right) {
+        (None, right) => right.clone(),
+        (left, None) => left.clone(),
+        (left, Some(right)) => {
+            let (next, right) = remove_min(right);
+            balance::<T, P>(next, left.clone(), right)
+        }
+    };
+    Some(rebuild(path, replacement))
+}
+
+// Return the smallest element of a subtree and the subtree without it.
+fn remove_min<T: Clone, P: PointerKind>(mut node: &Node<T, P>) -> (T, Link<T, P>) {
+    let mut path = vec![];
+    while let Some(ref left) = node.left {
+        path.push((node, Side::Left));
+        node = left;
+    }
+    (node.elem.clone(), rebuild(path, node.right.clone()))
+}
+
+// Free the nodes only this link owns, keeping their children on a stack
+// rather than letting them drop recursively. A node which is still shared
+// keeps its whole subtree alive, so it is not visited any further.
+fn drop_link<T, P: PointerKind>(link: Link<T, P>) {
+    let mut stack: Vec<_> = link.into_iter().collect();
+    while let Some(pointer) = stack.pop() {
+        if let Some(mut node) = P::try_unwrap(pointer) {
+            stack.extend(node.left.take());
+            stack.extend(node.right.take());
+        }
+    }
+}
+
+impl<T, P: PointerKind> Drop for PersistentBST<T, P> {
+    fn drop(&mut self) {
+        drop_link::<T, P>(self.root.take());
+    }
+}
+
+// ============================================== Iter
+
+pub struct Iter<'a, T: 'a, P: PointerKind + 'a> {
+    stack: Vec<&'a Node<T, P>>
+}
+
+impl<'a, T: 'a, P: PointerKind + 'a> Iter<'a, T, P> {
+    fn push_left_edge(&mut self, mut link: Option<&'a Node<T, P>>) {
+        while let Some(node) = link {
+            self.stack.push(node);
+            link = node.left.as_deref();
+        }
+    }
+}
+
+impl<T, P: PointerKind> PersistentBST<T, P> {
+    pub fn iter(&self) -> Iter<'_, T, P> {
+        let mut iter = Iter {
+            stack: vec![]
+        };
+        iter.push_left_edge(self.root.as_deref());
+        iter
+    }
+}
+
+impl<'a, T: 'a, P: PointerKind + 'a> IntoIterator for &'a PersistentBST<T, P> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, P>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T: 'a, P: PointerKind + 'a> Iterator for Iter<'a, T, P> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.stack.pop().map(|node| {
+            self.push_left_edge(node.right.as_deref());
+            &node.elem
+        })
+    }
+}
+
+// ============================================== Traits
+
+impl<T: fmt::Debug, P: PointerKind> fmt::Debug for PersistentBST<T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, P: PointerKind> PartialEq for PersistentBST<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, P: PointerKind> Eq for PersistentBST<T, P> {}
+
+impl<T: Hash, P: PointerKind> Hash for PersistentBST<T, P> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for elem in self {
+            elem.hash(state);
+        }
+        state.write_usize(self.len);
+    }
+}
+
+impl<T: Ord + Clone, P: PointerKind> FromIterator<T> for PersistentBST<T, P> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tree = PersistentBST::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<T: Ord + Clone, P: PointerKind> Extend<T> for PersistentBST<T, P> {
+    /// Replace this version of the tree with one which also holds the
+    /// elements. Other versions are not changed.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            *self = self.insert(elem);
+        }
+    }
+}
+
+// ============================================== Test
+
+#[cfg(test)]
+mod test {
+    use super::{ArcBST, Link, PersistentBST, RcKind};
+    use std::rc::Rc;
+    use std::thread;
+
+    #[test]
+    fn basics() {
+        let empty: PersistentBST<i32> = PersistentBST::new();
+        assert_eq!(empty.search(&1), false);
+        assert_eq!(empty.is_empty(), true);
+
+        let tree = empty.insert(2).insert(1).insert(3);
+        assert_eq!(tree.search(&1), true);
+        assert_eq!(tree.search(&4), false);
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        // The old versions are untouched
+        assert_eq!(empty.is_empty(), true);
+
+        let removed = tree.remove(&2);
+        assert_eq!(removed.iter().cloned().collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        // Check removing a missing element
+        assert_eq!(removed.remove(&5).iter().count(), 2);
+    }
+
+    // Check the heights and the balance of every node, and return the height.
+    fn check_balance(link: &Link<i32, RcKind>) -> usize {
+        link.as_ref().map_or(0, |node| {
+            let (left, right) = (check_balance(&node.left), check_balance(&node.right));
+            assert!(left <= right + 1 && right <= left + 1);
+            assert_eq!(node.height, 1 + left.max(right));
+            node.height
+        })
+    }
+
+    #[test]
+    fn balanced() {
+        // Sorted input would make a plain BST a linked list
+        const N: i32 = 10_000;
+        let tree: PersistentBST<i32> = (0..N).collect();
+        let height = check_balance(&tree.root);
+        assert!(height as f64 <= 1.45 * ((N + 2) as f64).log2());
+        assert_eq!(tree.len(), N as usize);
+
+        // Remove every other element, and check the balance along the way
+        let mut smaller = tree.clone();
+        for elem in (0..1000).map(|i| i * 2) {
+            smaller = smaller.remove(&elem);
+        }
+        check_balance(&smaller.root);
+        assert_eq!(smaller.len(), N as usize - 1000);
+        assert_eq!(smaller.search(&2), false);
+        assert_eq!(smaller.search(&3), true);
+
+        // Dropping either version frees only its own nodes
+        drop(tree);
+        assert_eq!(smaller.iter().take(3).cloned().collect::<Vec<_>>(), vec![1, 3, 5]);
+        drop(smaller);
+    }
+
+    #[test]
+    fn random_updates() {
+        let mut tree: PersistentBST<i32, RcKind> = PersistentBST::new();
+        let mut expected = ::std::collections::BTreeSet::new();
+        for i in 0..2000 {
+            let elem = (i * 7919) % 503;
+            if i % 3 == 0 {
+                tree = tree.remove(&elem);
+                expected.remove(&elem);
+            } else {
+                tree = tree.insert(elem);
+                expected.insert(elem);
+            }
+            assert_eq!(tree.len(), expected.len());
+        }
+        check_balance(&tree.root);
+        assert!(tree.iter().eq(expected.iter()));
+    }
+
+    #[test]
+    fn sharing() {
+        let tree: PersistentBST<i32> = PersistentBST::new().insert(4).insert(2).insert(6).insert(1).insert(3);
+        let left = tree.root.as_ref().and_then(|root| root.left.clone()).unwrap();
+        let right = tree.root.as_ref().and_then(|root| root.right.clone()).unwrap();
+
+        // Inserting on the right copies the path there only
+        let bigger = tree.insert(7);
+        let new_root = bigger.root.as_ref().unwrap();
+        assert_eq!(Rc::ptr_eq(new_root.left.as_ref().unwrap(), &left), true);
+        assert_eq!(Rc::ptr_eq(new_root.right.as_ref().unwrap(), &right), false);
+
+        // Inserting an existing element shares everything
+        let same = tree.insert(3);
+        assert_eq!(Rc::ptr_eq(same.root.as_ref().unwrap(), tree.root.as_ref().unwrap()), true);
+    }
+
+    #[test]
+    fn arc_snapshots() {
+        let tree: ArcBST<i32> = (0..100).fold(ArcBST::new(), |tree, elem| tree.insert((elem * 37) % 100));
+
+        let handles: Vec<_> = (0..4).map(|i| {
+            let snapshot = tree.clone();
+            thread::spawn(move || {
+                let updated = snapshot.remove(&i);
+                (snapshot.iter().count(), updated.iter().count())
+            })
+        }).collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), (100, 99));
+        }
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn traits() {
+        let tree: PersistentBST<i32> = vec![3, 1, 2].into_iter().collect();
+        assert_eq!(format!("{:?}", tree), "{1, 2, 3}");
+        assert_eq!(tree.len(), 3);
+
+        // Extending and clearing only change the version at hand
+        let mut copy = tree.clone();
+        copy.extend(vec![4, 1]);
+        assert_eq!(copy.len(), 4);
+        assert!(copy != tree);
+        assert_eq!(copy.remove(&4), tree);
+        copy.clear();
+        assert_eq!(copy.is_empty(), true);
+        assert_eq!(tree.len(), 3);
+    }
+}
@@ -1,11 +1,28 @@
+use std::borrow::Borrow;
+use std::cmp::{self, Ordering};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::iter::Peekable;
+use std::mem;
+use std::ops::{Bound, RangeBounds};
+use std::ptr;
+use std::slice;
 
-#[derive(Debug)]
-pub struct BST<T: PartialOrd> {
+use compare::{Compare, Natural};
+
+/// A binary search tree of unique elements, kept in the order given by the
+/// comparator `C`. By default elements are ordered by their `Ord`
+/// implementation.
+#[derive(Clone)]
+pub struct BST<T, C = Natural> {
     root: Link<T>,
+    cmp: C,
 }
 
-#[derive(Debug)]
-struct Node<T: PartialOrd> {
+#[derive(Clone, Debug)]
+struct Node<T> {
     elem: T,
     left: Link<T>,
     right: Link<T>,
@@ -13,314 +30,2460 @@ struct Node<T: PartialOrd> {
 
 type Link<T> = Option<Box<Node<T>>>;
 
-trait InsertSearch<T> {
-    fn insert(&mut self, elem: T) -> bool;
-    fn search(&self, elem: T) -> bool;
+trait InsertSearch<T, C> {
+    fn insert(&mut self, elem: T, cmp: &C) -> bool;
+    fn search<Q: ?Sized>(&self, elem: &Q, cmp: &C) -> bool where T: Borrow<Q>, C: Compare<Q>;
+    fn remove<Q: ?Sized>(&mut self, elem: &Q, cmp: &C) -> Link<T> where T: Borrow<Q>, C: Compare<Q>;
+}
+
+impl<T, C: Compare<T> + Default> Default for BST<T, C> {
+    fn default() -> Self {
+        BST::with_comparator(C::default())
+    }
 }
 
-impl<T: PartialOrd> BST<T> {
+impl<T: Ord> BST<T> {
     pub fn new() -> BST<T> {
-        BST { root: None }
+        BST::with_comparator(Natural)
+    }
+}
+
+impl<T, C: Compare<T>> BST<T, C> {
+    /// Create an empty BST whose elements are ordered by `cmp`, which may
+    /// be any `Compare` implementation or a closure such as
+    /// `|a: &String, b: &String| b.cmp(a)`.
+    pub fn with_comparator(cmp: C) -> BST<T, C> {
+        BST { root: None, cmp }
     }
 
     /// Insert an element into the BST. Return true
     /// if successful, or false if the element was already in the BST.
     pub fn insert(&mut self, elem: T) -> bool {
-        self.root.insert(elem)
+        InsertSearch::insert(&mut self.root, elem, &self.cmp)
     }
 
     /// Search for an element in the BST. Return true
     /// if the element was found.
-    pub fn search(&self, elem: T) -> bool {
-        self.root.search(elem)
+    ///
+    /// The element may be any borrowed form of the BST's element type,
+    /// e.g. a `&str` for a `BST<String>`.
+    pub fn search<Q: ?Sized>(&self, elem: &Q) -> bool where T: Borrow<Q>, C: Compare<Q> {
+        self.root.search(elem, &self.cmp)
+    }
+
+    /// Remove an element from the BST. Return true
+    /// if the element was in the BST.
+    pub fn remove<Q: ?Sized>(&mut self, elem: &Q) -> bool where T: Borrow<Q>, C: Compare<Q> {
+        self.root.remove(elem, &self.cmp).is_some()
+    }
+
+    /// Remove an element from the BST and return it, or None if the
+    /// element was not in the BST.
+    pub fn take<Q: ?Sized>(&mut self, elem: &Q) -> Option<T> where T: Borrow<Q>, C: Compare<Q> {
+        self.root.remove(elem, &self.cmp).map(|node| node.elem)
+    }
+
+    /// Return the smallest element in the BST, or None if it is empty.
+    pub fn min(&self) -> Option<&T> {
+        let mut cur = self.root.as_ref();
+        let mut found = None;
+        while let Some(node) = cur {
+            found = Some(&node.elem);
+            cur = node.left.as_ref();
+        }
+        found
+    }
+
+    /// Return the largest element in the BST, or None if it is empty.
+    pub fn max(&self) -> Option<&T> {
+        let mut cur = self.root.as_ref();
+        let mut found = None;
+        while let Some(node) = cur {
+            found = Some(&node.elem);
+            cur = node.right.as_ref();
+        }
+        found
+    }
+
+    /// Return the largest element which is less than or equal to `elem`.
+    pub fn floor<Q: ?Sized>(&self, elem: &Q) -> Option<&T> where T: Borrow<Q>, C: Compare<Q> {
+        let mut cur = self.root.as_ref();
+        let mut found = None;
+        while let Some(node) = cur {
+            match self.cmp.compare(elem, node.elem.borrow()) {
+                Ordering::Less => cur = node.left.as_ref(),
+                Ordering::Greater => {
+                    found = Some(&node.elem);
+                    cur = node.right.as_ref();
+                },
+                Ordering::Equal => return Some(&node.elem)
+            }
+        }
+        found
+    }
+
+    /// Return the smallest element which is greater than or equal to `elem`.
+    pub fn ceiling<Q: ?Sized>(&self, elem: &Q) -> Option<&T> where T: Borrow<Q>, C: Compare<Q> {
+        let mut cur = self.root.as_ref();
+        let mut found = None;
+        while let Some(node) = cur {
+            match self.cmp.compare(elem, node.elem.borrow()) {
+                Ordering::Less => {
+                    found = Some(&node.elem);
+                    cur = node.left.as_ref();
+                },
+                Ordering::Greater => cur = node.right.as_ref(),
+                Ordering::Equal => return Some(&node.elem)
+            }
+        }
+        found
+    }
+
+    /// Return the largest element which is strictly less than `elem`.
+    /// `elem` itself does not need to be in the BST.
+    pub fn predecessor<Q: ?Sized>(&self, elem: &Q) -> Option<&T> where T: Borrow<Q>, C: Compare<Q> {
+        let mut cur = self.root.as_ref();
+        let mut found = None;
+        while let Some(node) = cur {
+            if self.cmp.compare(node.elem.borrow(), elem) == Ordering::Less {
+                found = Some(&node.elem);
+                cur = node.right.as_ref();
+            } else {
+                cur = node.left.as_ref();
+            }
+        }
+        found
+    }
+
+    /// Return the smallest element which is strictly greater than `elem`.
+    /// `elem` itself does not need to be in the BST.
+    pub fn successor<Q: ?Sized>(&self, elem: &Q) -> Option<&T> where T: Borrow<Q>, C: Compare<Q> {
+        let mut cur = self.root.as_ref();
+        let mut found = None;
+        while let Some(node) = cur {
+            if self.cmp.compare(node.elem.borrow(), elem) == Ordering::Greater {
+                found = Some(&node.elem);
+                cur = node.left.as_ref();
+            } else {
+                cur = node.right.as_ref();
+            }
+        }
+        found
     }
 }
 
-impl<T: PartialOrd> InsertSearch<T> for Link<T> {
-    fn insert(&mut self, elem: T) -> bool {
+impl<T, C: Compare<T>> InsertSearch<T, C> for Link<T> {
+    fn insert(&mut self, elem: T, cmp: &C) -> bool {
         match *self {
             None => {
                 let node = Node {
-                    elem: elem,
+                    elem,
                     left: None,
                     right: None
                 };
-                self.get_or_insert(Box::new(node));
-                return true;
+                *self = Some(Box::new(node));
+                true
             },
             Some(ref mut boxed_node) => {
                 let node = boxed_node.as_mut();
-                if elem < node.elem {
-                    node.left.insert(elem)
-                } else if elem > node.elem {
-                    node.right.insert(elem)
-                } else {
-                    false
+                match cmp.compare(&elem, &node.elem) {
+                    Ordering::Less => InsertSearch::insert(&mut node.left, elem, cmp),
+                    Ordering::Greater => InsertSearch::insert(&mut node.right, elem, cmp),
+                    Ordering::Equal => false
                 }
             }
         }
     }
 
-    fn search(&self, elem: T) -> bool {
+    fn search<Q: ?Sized>(&self, elem: &Q, cmp: &C) -> bool where T: Borrow<Q>, C: Compare<Q> {
         match *self {
             None => false,
             Some(ref boxed_node) => {
                 let node = boxed_node.as_ref();
-                if elem < node.elem {
-                    node.left.search(elem)
-                } else if elem > node.elem {
-                    node.right.search(elem)
-                } else {
-                    true
+                match cmp.compare(elem, node.elem.borrow()) {
+                    Ordering::Less => node.left.search(elem, cmp),
+                    Ordering::Greater => node.right.search(elem, cmp),
+                    Ordering::Equal => true
                 }
             }
         }
     }
+
+    fn remove<Q: ?Sized>(&mut self, elem: &Q, cmp: &C) -> Link<T> where T: Borrow<Q>, C: Compare<Q> {
+        let order = match *self {
+            None => return None,
+            Some(ref boxed_node) => cmp.compare(elem, boxed_node.elem.borrow())
+        };
+        match order {
+            Ordering::Less => self.as_mut().and_then(|node| node.left.remove(elem, cmp)),
+            Ordering::Greater => self.as_mut().and_then(|node| node.right.remove(elem, cmp)),
+            Ordering::Equal => unlink(self)
+        }
+    }
+}
+
+// The links of a node, which is all the removal helpers below need. Trees
+// which keep more about a subtree in their nodes, like `interval`, share
+// the helpers by implementing `update`.
+pub(crate) trait Children: Sized {
+    fn left(&mut self) -> &mut Option<Box<Self>>;
+    fn right(&mut self) -> &mut Option<Box<Self>>;
+
+    // Recompute what the node keeps about its subtree, once its children
+    // are up to date.
+    fn update(&mut self) {}
+}
+
+impl<T> Children for Node<T> {
+    fn left(&mut self) -> &mut Link<T> {
+        &mut self.left
+    }
+
+    fn right(&mut self) -> &mut Link<T> {
+        &mut self.right
+    }
+}
+
+// Detach the node at `link`, putting its in-order successor (or its only
+// child) in its place. The returned node has no children.
+pub(crate) fn unlink<N: Children>(link: &mut Option<Box<N>>) -> Option<Box<N>> {
+    let mut node = link.take()?;
+    *link = match (node.left().take(), node.right().take()) {
+        (None, right) => right,
+        (left, None) => left,
+        (left, mut right) => {
+            let mut next = take_min(&mut right);
+            if let Some(ref mut next) = next {
+                *next.left() = left;
+                *next.right() = right;
+                next.update();
+            }
+            next
+        }
+    };
+    Some(node)
+}
+
+// Detach the node holding the smallest element of a subtree, putting its
+// right subtree in its place. The nodes on the way down are taken off the
+// tree and put back from the bottom up, so each is updated after its child.
+pub(crate) fn take_min<N: Children>(link: &mut Option<Box<N>>) -> Option<Box<N>> {
+    let mut path = vec![];
+    let mut cur = link.take();
+    while let Some(mut node) = cur {
+        cur = node.left().take();
+        path.push(node);
+    }
+    let mut min = path.pop()?;
+    let mut rest = min.right().take();
+    while let Some(mut node) = path.pop() {
+        *node.left() = rest;
+        node.update();
+        rest = Some(node);
+    }
+    *link = rest;
+    Some(min)
 }
 
 // ============================================== IntoIter
 
-impl<T: PartialOrd> IntoIterator for BST<T> {
+impl<T, C> IntoIterator for BST<T, C> {
     type Item = T;
     type IntoIter = IntoIter<T>;
     fn into_iter(self) -> Self::IntoIter {
-        IntoIter {
-            stack: vec![],
-            tree: self
-        }
+        IntoIter::new(self.root)
     }
 }
 
-pub struct IntoIter<T: PartialOrd> {
-    stack: Vec<Box<Node<T>>>,
-    tree: BST<T>
+pub struct IntoIter<T> {
+    stack: Vec<Box<Node<T>>>
 }
 
-impl<T: PartialOrd> IntoIter<T> {
-    fn find_next(&mut self, mut node: Box<Node<T>>) -> T {
-        if let Some(left_node) = node.left.take() {
-            if let Some(right_node) = node.right.take() {
-                self.stack.push(right_node);
-            }
-            self.find_next(left_node)
-        } else if let Some(right_node) = node.right.take() {
-            self.stack.push(right_node);
-            node.elem
-        } else {
-            node.elem
+impl<T> IntoIter<T> {
+    fn new(root: Link<T>) -> IntoIter<T> {
+        let mut iter = IntoIter {
+            stack: vec![]
+        };
+        iter.push_left_edge(root);
+        iter
+    }
+
+    // Push the node and all of its left descendants, so that the
+    // smallest remaining element ends up on the top of the stack.
+    fn push_left_edge(&mut self, mut link: Link<T>) {
+        while let Some(mut node) = link {
+            link = node.left.take();
+            self.stack.push(node);
         }
     }
 }
 
-impl<T: PartialOrd> Iterator for IntoIter<T> {
+impl<T> Iterator for IntoIter<T> {
     type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let next_start = self.stack.pop();
-        if let Some(next_start) = next_start {
-            Some(self.find_next(next_start))
-        } else {
-            if let Some(node) = self.tree.root.take() {
-                Some(self.find_next(node))
-            } else {
-                None
-            }
-        }
+        self.stack.pop().map(|node| {
+            let node = *node;
+            self.push_left_edge(node.right);
+            node.elem
+        })
     }
 }
 
 // ============================================== Iter
 
-pub struct Iter<'a, T: PartialOrd + 'a> {
+pub struct Iter<'a, T: 'a> {
     stack: Vec<&'a Node<T>>
 }
 
-impl<'a, T: PartialOrd + 'a> Iter<'a, T> {
-    fn find_next(&mut self, node: &'a Node<T>) -> &'a T {
-        if let Some(ref left_node) = node.left {
-            if let Some(ref right_node) = node.right {
-                self.stack.push(right_node);
-            }
-            self.find_next(left_node)
-        } else if let Some(ref right_node) = node.right {
-            self.stack.push(right_node);
-            &node.elem
-        } else {
-            &node.elem
+impl<'a, T: 'a> Iter<'a, T> {
+    fn push_left_edge(&mut self, mut link: Option<&'a Node<T>>) {
+        while let Some(node) = link {
+            self.stack.push(node);
+            link = node.left.as_deref();
         }
     }
 }
 
-impl<'a, T: PartialOrd + 'a> IntoIterator for &'a BST<T> {
+impl<T, C> BST<T, C> {
+    /// Return the number of elements in the BST. The size is not stored,
+    /// so this takes time proportional to it.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Return true if the BST has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Remove all the elements.
+    pub fn clear(&mut self) {
+        self.root = None;
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut iter = Iter {
+            stack: vec![]
+        };
+        iter.push_left_edge(self.root.as_deref());
+        iter
+    }
+}
+
+impl<'a, T: 'a, C> IntoIterator for &'a BST<T, C> {
     type Item = &'a T;
     type IntoIter = Iter<'a, T>;
     fn into_iter(self) -> Self::IntoIter {
-        let mut stack = vec![];
-        if let Some(ref node) = self.root {
-            stack.push(node.as_ref());
+        self.iter()
+    }
+}
+
+impl<'a, T: 'a> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.stack.pop().map(|node| {
+            self.push_left_edge(node.right.as_deref());
+            &node.elem
+        })
+    }
+}
+
+// ============================================== Range
+
+impl<T, C: Compare<T>> BST<T, C> {
+    /// Visit the elements within `range` in ascending order. Like `search`,
+    /// the bounds may be any borrowed form of the element type.
+    pub fn range<Q: ?Sized, R: RangeBounds<Q>>(&self, range: R) -> Range<'_, T>
+        where T: Borrow<Q>, C: Compare<Q>
+    {
+        let mut iter = Iter {
+            stack: vec![]
+        };
+
+        // Walk down to the first element within the lower bound, keeping
+        // the nodes still to be visited on the stack like `Iter` does.
+        let mut cur = self.root.as_deref();
+        while let Some(node) = cur {
+            if above_lower(&self.cmp, node.elem.borrow(), range.start_bound()) {
+                iter.stack.push(node);
+                cur = node.left.as_deref();
+            } else {
+                cur = node.right.as_deref();
+            }
+        }
+
+        // Find the last element within the upper bound, where to stop.
+        let mut last = None;
+        let mut cur = self.root.as_deref();
+        while let Some(node) = cur {
+            if below_upper(&self.cmp, node.elem.borrow(), range.end_bound()) {
+                last = Some(&node.elem);
+                cur = node.right.as_deref();
+            } else {
+                cur = node.left.as_deref();
+            }
+        }
+
+        let first = iter.stack.last().map(|node| &node.elem);
+        if let (Some(first), Some(end)) = (first, last) {
+            if self.cmp.compare(first, end) == Ordering::Greater {
+                last = None;
+            }
         }
-        Iter {
-            stack: stack
+        Range {
+            iter,
+            last
         }
     }
 }
 
-impl<'a, T: PartialOrd + 'a> Iterator for Iter<'a, T> {
+pub(crate) fn above_lower<Q: ?Sized, C: Compare<Q>>(cmp: &C, elem: &Q, bound: Bound<&Q>) -> bool {
+    match bound {
+        Bound::Included(start) => cmp.compare(elem, start) != Ordering::Less,
+        Bound::Excluded(start) => cmp.compare(elem, start) == Ordering::Greater,
+        Bound::Unbounded => true
+    }
+}
+
+pub(crate) fn below_upper<Q: ?Sized, C: Compare<Q>>(cmp: &C, elem: &Q, bound: Bound<&Q>) -> bool {
+    match bound {
+        Bound::Included(end) => cmp.compare(elem, end) != Ordering::Greater,
+        Bound::Excluded(end) => cmp.compare(elem, end) == Ordering::Less,
+        Bound::Unbounded => true
+    }
+}
+
+pub struct Range<'a, T: 'a> {
+    iter: Iter<'a, T>,
+    // The last element to yield, or None once the range is exhausted.
+    last: Option<&'a T>
+}
+
+impl<'a, T: 'a> Iterator for Range<'a, T> {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let next_start = self.stack.pop();
-        if let Some(next_start) = next_start {
-            Some(self.find_next(next_start))
-        } else {
-            None
+        let last = self.last?;
+        let elem = self.iter.next()?;
+        if ptr::eq(elem, last) {
+            self.last = None;
         }
+        Some(elem)
     }
 }
 
 // ============================================== IterMut
 
-pub struct IterMut<'a, T: PartialOrd + 'a> {
-    stack: Vec<&'a mut Node<T>>
+// Mutable references to the elements themselves are not handed out, since
+// changing an element could break the ordering of the tree. `BSTMap` uses
+// this to give out mutable references to its values only.
+struct IterMut<'a, T: 'a> {
+    // Each entry holds a node's element together with its right subtree,
+    // which is split off so the left subtree can be borrowed separately.
+    stack: Vec<(&'a mut T, Option<&'a mut Node<T>>)>
 }
 
-impl<'a, T: PartialOrd + 'a> IterMut<'a, T> {
-    fn find_next(&mut self, node: &'a mut Node<T>) -> &'a mut T {
-        if let Some(ref mut left_node) = node.left {
-            if let Some(ref mut right_node) = node.right {
-                self.stack.push(right_node);
-            }
-            self.find_next(left_node)
-        } else if let Some(ref mut right_node) = node.right {
-            self.stack.push(right_node);
-            &mut node.elem
-        } else {
-            &mut node.elem
+impl<'a, T: 'a> IterMut<'a, T> {
+    fn push_left_edge(&mut self, mut link: Option<&'a mut Node<T>>) {
+        while let Some(node) = link {
+            let Node { ref mut elem, ref mut left, ref mut right } = *node;
+            self.stack.push((elem, right.as_deref_mut()));
+            link = left.as_deref_mut();
         }
     }
 }
 
-impl<'a, T: PartialOrd + 'a> IntoIterator for &'a mut BST<T> {
+impl<T, C> BST<T, C> {
+    fn iter_mut(&mut self) -> IterMut<'_, T> {
+        let mut iter = IterMut {
+            stack: vec![]
+        };
+        iter.push_left_edge(self.root.as_deref_mut());
+        iter
+    }
+}
+
+impl<'a, T: 'a> Iterator for IterMut<'a, T> {
     type Item = &'a mut T;
-    type IntoIter = IterMut<'a, T>;
-    fn into_iter(self) -> Self::IntoIter {
-        let mut stack = vec![];
-        if let Some(ref mut node) = self.root {
-            stack.push(node.as_mut());
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.stack.pop().map(|(elem, right)| {
+            self.push_left_edge(right);
+            elem
+        })
+    }
+}
+
+// ============================================== Traversals
+
+impl<T, C> BST<T, C> {
+    /// Visit the elements in pre-order: every node before its left subtree,
+    /// and that before its right subtree. Inserting the elements in this
+    /// order into an empty tree rebuilds the same shape.
+    pub fn iter_preorder(&self) -> Preorder<'_, T> {
+        Preorder {
+            stack: self.root.as_deref().into_iter().collect()
+        }
+    }
+
+    /// Visit the elements in post-order: both subtrees of a node before the
+    /// node itself, so children always come before their parent.
+    pub fn iter_postorder(&self) -> Postorder<'_, T> {
+        Postorder {
+            stack: self.root.as_deref().map(|node| (node, false)).into_iter().collect()
         }
-        IterMut {
-            stack: stack
+    }
+
+    /// Visit the elements level by level from the root, each level from
+    /// left to right. Call `with_depth` on the iterator to get the depth of
+    /// every element too.
+    pub fn iter_level_order(&self) -> LevelOrder<'_, T> {
+        LevelOrder {
+            queue: self.root.as_deref().map(|node| (0, node)).into_iter().collect()
         }
     }
 }
 
-impl<'a, T: PartialOrd + 'a> Iterator for IterMut<'a, T> {
-    type Item = &'a mut T;
+pub struct Preorder<'a, T: 'a> {
+    stack: Vec<&'a Node<T>>
+}
+
+impl<'a, T: 'a> Iterator for Preorder<'a, T> {
+    type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let next_start = self.stack.pop();
-        if let Some(next_start) = next_start {
-            Some(self.find_next(next_start))
-        } else {
-            None
+        self.stack.pop().map(|node| {
+            // Push the right child first, so the left one is visited first.
+            self.stack.extend(node.right.as_deref());
+            self.stack.extend(node.left.as_deref());
+            &node.elem
+        })
+    }
+}
+
+pub struct Postorder<'a, T: 'a> {
+    // Each node is pushed twice: first to have its children pushed above
+    // it, and then, marked as expanded, to be yielded after them.
+    stack: Vec<(&'a Node<T>, bool)>
+}
+
+impl<'a, T: 'a> Iterator for Postorder<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, expanded)) = self.stack.pop() {
+            if expanded {
+                return Some(&node.elem);
+            }
+            self.stack.push((node, true));
+            self.stack.extend(node.right.as_deref().map(|right| (right, false)));
+            self.stack.extend(node.left.as_deref().map(|left| (left, false)));
         }
+        None
     }
 }
 
-// ============================================== Test
+pub struct LevelOrder<'a, T: 'a> {
+    queue: VecDeque<(usize, &'a Node<T>)>
+}
 
-#[cfg(test)]
-mod test {
-    use super::BST;
+impl<'a, T: 'a> LevelOrder<'a, T> {
+    /// Yield the depth of each element along with it, where the root has
+    /// depth 0.
+    pub fn with_depth(self) -> WithDepth<'a, T> {
+        WithDepth(self)
+    }
 
-    #[test]
-    fn basics() {
-        let mut tree = BST::new();
+    fn next_with_depth(&mut self) -> Option<(usize, &'a T)> {
+        self.queue.pop_front().map(|(depth, node)| {
+            self.queue.extend(node.left.as_deref().map(|left| (depth + 1, left)));
+            self.queue.extend(node.right.as_deref().map(|right| (depth + 1, right)));
+            (depth, &node.elem)
+        })
+    }
+}
 
-        // Check empty list behaves right
-        assert_eq!(tree.search(1), false);
+impl<'a, T: 'a> Iterator for LevelOrder<'a, T> {
+    type Item = &'a T;
 
-        // Populate list
-        assert_eq!(tree.insert(1), true);
-        assert_eq!(tree.insert(2), true);
-        assert_eq!(tree.insert(3), true);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_depth().map(|(_, elem)| elem)
+    }
+}
 
-        // Check search
-        assert_eq!(tree.search(1), true);
-        assert_eq!(tree.search(2), true);
-        assert_eq!(tree.search(3), true);
-        assert_eq!(tree.search(4), false);
+pub struct WithDepth<'a, T: 'a>(LevelOrder<'a, T>);
 
-        // Check repeatly insertion
-        assert_eq!(tree.insert(1), false);
+impl<'a, T: 'a> Iterator for WithDepth<'a, T> {
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_with_depth()
     }
+}
 
-    #[test]
-    fn test_into_iter() {
-        let mut tree = BST::new();
+// ============================================== Cursor
 
-        // Populate list
-        assert_eq!(tree.insert(1), true);
-        assert_eq!(tree.insert(2), true);
-        assert_eq!(tree.insert(3), true);
+impl<T, C: Compare<T>> BST<T, C> {
+    /// Return a cursor at the smallest element, or at the ghost position
+    /// if the BST is empty.
+    pub fn cursor_front(&self) -> Cursor<'_, T, C> {
+        let mut cursor = Cursor { tree: self, path: vec![] };
+        cursor.move_next();
+        cursor
+    }
 
-        // Check into_iterator
-        let mut iter = tree.into_iter();
-        assert_eq!(iter.next(), Some(1));
-        assert_eq!(iter.next(), Some(2));
-        assert_eq!(iter.next(), Some(3));
-        assert_eq!(iter.next(), None);
+    /// Return a cursor at the largest element, or at the ghost position
+    /// if the BST is empty.
+    pub fn cursor_back(&self) -> Cursor<'_, T, C> {
+        let mut cursor = Cursor { tree: self, path: vec![] };
+        cursor.move_prev();
+        cursor
     }
 
-    #[test]
-    fn test_iter() {
-        let mut tree = BST::new();
+    /// Return a cursor at `elem`, or at the smallest element greater than
+    /// it if `elem` is not in the BST. The cursor is at the ghost position
+    /// if there is no such element.
+    pub fn cursor_at<Q: ?Sized>(&self, elem: &Q) -> Cursor<'_, T, C> where T: Borrow<Q>, C: Compare<Q> {
+        // Like `ceiling`, but remember the path down to the found node.
+        let mut path = vec![];
+        let mut found = 0;
+        let mut cur = self.root.as_deref();
+        while let Some(node) = cur {
+            path.push(node);
+            match self.cmp.compare(elem, node.elem.borrow()) {
+                Ordering::Less => {
+                    found = path.len();
+                    cur = node.left.as_deref();
+                },
+                Ordering::Greater => cur = node.right.as_deref(),
+                Ordering::Equal => {
+                    found = path.len();
+                    break;
+                }
+            }
+        }
+        path.truncate(found);
+        Cursor { tree: self, path }
+    }
 
-        // Populate list
-        assert_eq!(tree.insert(1), true);
-        assert_eq!(tree.insert(2), true);
-        assert_eq!(tree.insert(3), true);
+    /// Like `cursor_front`, but the cursor can also edit the BST.
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T, C> {
+        let path = self.cursor_front().sides();
+        CursorMut { tree: self, path }
+    }
 
-        // Check into_iterator
-        {
-            let mut iter = (&tree).into_iter();
-            assert_eq!(iter.next(), Some(&1));
-            assert_eq!(iter.next(), Some(&2));
-            assert_eq!(iter.next(), Some(&3));
-            assert_eq!(iter.next(), None);
+    /// Like `cursor_back`, but the cursor can also edit the BST.
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, T, C> {
+        let path = self.cursor_back().sides();
+        CursorMut { tree: self, path }
+    }
+
+    /// Like `cursor_at`, but the cursor can also edit the BST.
+    pub fn cursor_at_mut<Q: ?Sized>(&mut self, elem: &Q) -> CursorMut<'_, T, C> where T: Borrow<Q>, C: Compare<Q> {
+        let path = self.cursor_at(elem).sides();
+        CursorMut { tree: self, path }
+    }
+}
+
+fn child<T>(node: &Node<T>, side: Side) -> Option<&Node<T>> {
+    match side {
+        Side::Left => node.left.as_deref(),
+        Side::Right => node.right.as_deref()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    pub(crate) fn other(self) -> Side {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left
         }
+    }
+}
 
-        // Check repeatly insertion
-        assert_eq!(tree.insert(1), false);
+/// A position in a BST from which the elements around it can be visited in
+/// order, without searching from the root again.
+///
+/// A cursor is either at an element or at the "ghost" position, which sits
+/// after the largest element and before the smallest one. Moving past
+/// either end reaches the ghost position, and moving on from there wraps
+/// around to the other end.
+pub struct Cursor<'a, T: 'a, C: 'a = Natural> {
+    tree: &'a BST<T, C>,
+    // The nodes from the root down to the current one, or nothing at the
+    // ghost position.
+    path: Vec<&'a Node<T>>,
+}
+
+impl<'a, T: 'a, C: 'a> Clone for Cursor<'a, T, C> {
+    fn clone(&self) -> Self {
+        Cursor { tree: self.tree, path: self.path.clone() }
     }
+}
 
-    #[test]
-    fn test_iter_mut() {
-        let mut tree = BST::new();
+impl<'a, T: 'a, C: 'a> Cursor<'a, T, C> {
+    /// Return the element at the cursor, or None at the ghost position.
+    pub fn current(&self) -> Option<&'a T> {
+        self.path.last().map(|node| &node.elem)
+    }
 
-        // Populate list
-        assert_eq!(tree.insert(1), true);
-        assert_eq!(tree.insert(2), true);
-        assert_eq!(tree.insert(3), true);
+    /// Move to the next element in order.
+    pub fn move_next(&mut self) {
+        self.step(Side::Right)
+    }
 
-        // Check into_iterator
-        {
-            let mut iter = (&mut tree).into_iter();
-            assert_eq!(iter.next(), Some(&mut 1));
-            assert_eq!(iter.next(), Some(&mut 2));
-            
-            let elem = iter.next(); // it should be 3
-            assert_eq!(elem, Some(&mut 3));
-            
-            // change the value to 4
-            if let Some(value) = elem {
-                *value = 4;
+    /// Move to the previous element in order.
+    pub fn move_prev(&mut self) {
+        self.step(Side::Left)
+    }
+
+    /// Return the next element without moving the cursor.
+    pub fn peek_next(&self) -> Option<&'a T> {
+        let mut cursor = self.clone();
+        cursor.move_next();
+        cursor.current()
+    }
+
+    /// Return the previous element without moving the cursor.
+    pub fn peek_prev(&self) -> Option<&'a T> {
+        let mut cursor = self.clone();
+        cursor.move_prev();
+        cursor.current()
+    }
+
+    // Move to the neighbour on `side`: the next element for `Right`, and
+    // the previous one for `Left`.
+    fn step(&mut self, side: Side) {
+        let next = match self.path.last() {
+            None => self.tree.root.as_deref(),
+            Some(node) => child(node, side)
+        };
+        if let Some(node) = next {
+            // Go down one step toward `side`, and then as far as possible
+            // the other way. From the ghost position this starts at the root.
+            self.path.push(node);
+            while let Some(node) = child(self.path[self.path.len() - 1], side.other()) {
+                self.path.push(node);
+            }
+            return;
+        }
+        // Otherwise go up until the path comes from the other side of a
+        // node, which is the neighbour. Leaving the root means there is none.
+        while let Some(node) = self.path.pop() {
+            match self.path.last() {
+                Some(parent) if child(parent, side.other()).is_some_and(|other| ptr::eq(other, node)) => return,
+                Some(_) => {},
+                None => return
             }
         }
+    }
 
-        // Check insertion
-        assert_eq!(tree.insert(1), false);
-        assert_eq!(tree.insert(3), true);
-        assert_eq!(tree.insert(4), false);
+    // The sides taken from the root down to the current node, or None at
+    // the ghost position.
+    fn sides(&self) -> Option<Vec<Side>> {
+        if self.path.is_empty() {
+            return None;
+        }
+        Some(self.path.windows(2).map(|pair| {
+            if child(pair[0], Side::Left).is_some_and(|left| ptr::eq(left, pair[1])) {
+                Side::Left
+            } else {
+                Side::Right
+            }
+        }).collect())
+    }
+}
+
+/// A cursor which can also remove the element it is at, and insert
+/// elements right next to it.
+///
+/// It remembers the way down from the root instead of the nodes on it, so
+/// each operation walks that way again and takes time proportional to the
+/// height of the tree, but does no comparisons to find its place.
+pub struct CursorMut<'a, T: 'a, C: 'a = Natural> {
+    tree: &'a mut BST<T, C>,
+    // The sides taken from the root down to the current node, or None at
+    // the ghost position.
+    path: Option<Vec<Side>>,
+}
+
+impl<'a, T: 'a, C: Compare<T> + 'a> CursorMut<'a, T, C> {
+    // Return a read-only cursor at the same position.
+    fn as_cursor(&self) -> Cursor<'_, T, C> {
+        let mut path = vec![];
+        if let Some(ref sides) = self.path {
+            let mut node = self.tree.root.as_deref().expect("cursor path in an empty tree");
+            path.push(node);
+            for side in sides {
+                node = child(node, *side).expect("cursor path to a missing node");
+                path.push(node);
+            }
+        }
+        Cursor { tree: self.tree, path }
+    }
+
+    /// Return the element at the cursor, or None at the ghost position.
+    pub fn current(&self) -> Option<&T> {
+        self.as_cursor().current()
+    }
+
+    /// Move to the next element in order.
+    pub fn move_next(&mut self) {
+        let mut cursor = self.as_cursor();
+        cursor.move_next();
+        self.path = cursor.sides();
+    }
+
+    /// Move to the previous element in order.
+    pub fn move_prev(&mut self) {
+        let mut cursor = self.as_cursor();
+        cursor.move_prev();
+        self.path = cursor.sides();
     }
-}
\ No newline at end of file
+
+    /// Return the next element without moving the cursor.
+    pub fn peek_next(&self) -> Option<&T> {
+        self.as_cursor().peek_next()
+    }
+
+    /// Return the previous element without moving the cursor.
+    pub fn peek_prev(&self) -> Option<&T> {
+        self.as_cursor().peek_prev()
+    }
+
+    /// Remove the element at the cursor and return it, moving the cursor
+    /// to the next element. Return None at the ghost position.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let mut sides = self.path.take()?;
+        let mut link = &mut self.tree.root;
+        for side in &sides {
+            let node = link.as_mut().expect("cursor path to a missing node");
+            link = match *side {
+                Side::Left => &mut node.left,
+                Side::Right => &mut node.right
+            };
+        }
+        let (has_left, has_right) = match *link {
+            Some(ref node) => (node.left.is_some(), node.right.is_some()),
+            None => panic!("cursor path to a missing node")
+        };
+        let node = unlink(link)?;
+
+        // `unlink` moves the successor into the removed node's place if it
+        // had two children. With a right child only, the successor is the
+        // smallest element of that child's subtree, which is now in place.
+        // Without a right child, it is the nearest ancestor on the right.
+        if has_right {
+            if !has_left {
+                let mut cur = link.as_deref();
+                while let Some(left) = cur.and_then(|node| node.left.as_deref()) {
+                    sides.push(Side::Left);
+                    cur = Some(left);
+                }
+            }
+            self.path = Some(sides);
+        } else {
+            while let Some(side) = sides.pop() {
+                if side == Side::Left {
+                    self.path = Some(sides);
+                    break;
+                }
+            }
+        }
+        Some(node.elem)
+    }
+
+    /// Insert `elem` right after the element at the cursor, without moving
+    /// the cursor. At the ghost position, insert it before the smallest
+    /// element.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `elem` would not be between the element at the cursor and
+    /// the next one.
+    pub fn insert_after(&mut self, elem: T) {
+        {
+            let cursor = self.as_cursor();
+            assert!(self.in_order(cursor.current(), &elem, cursor.peek_next()),
+                    "insert_after requires the element to be in order");
+        }
+        // A new element is always added as a leaf, so the path to the
+        // current node stays the same.
+        InsertSearch::insert(&mut self.tree.root, elem, &self.tree.cmp);
+    }
+
+    /// Insert `elem` right before the element at the cursor, without moving
+    /// the cursor. At the ghost position, insert it after the largest
+    /// element.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `elem` would not be between the previous element and the
+    /// element at the cursor.
+    pub fn insert_before(&mut self, elem: T) {
+        {
+            let cursor = self.as_cursor();
+            assert!(self.in_order(cursor.peek_prev(), &elem, cursor.current()),
+                    "insert_before requires the element to be in order");
+        }
+        InsertSearch::insert(&mut self.tree.root, elem, &self.tree.cmp);
+    }
+
+    // Return true if `elem` is strictly between `prev` and `next`, where a
+    // missing neighbour is no bound.
+    fn in_order(&self, prev: Option<&T>, elem: &T, next: Option<&T>) -> bool {
+        prev.is_none_or(|prev| self.tree.cmp.compare(prev, elem) == Ordering::Less)
+            && next.is_none_or(|next| self.tree.cmp.compare(elem, next) == Ordering::Less)
+    }
+}
+
+// ============================================== Visualization
+
+/// Extra information to show on every node of a DOT graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Annotation {
+    /// The height of the node's subtree, where a leaf has height 1.
+    Height,
+    /// The number of elements in the node's subtree.
+    Size,
+    /// The height of the right subtree minus the height of the left one.
+    Balance,
+}
+
+/// A summary of the shape of a BST.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub size: usize,
+    /// The number of nodes on the longest path from the root, or 0 for an
+    /// empty tree.
+    pub height: usize,
+    /// How many nodes have each balance factor, the height of their right
+    /// subtree minus the height of their left one.
+    pub balance: BTreeMap<isize, usize>,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "size {}, height {}, balance factors", self.size, self.height)?;
+        for (factor, count) in &self.balance {
+            write!(f, " [{}: {}]", factor, count)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: fmt::Display, C> BST<T, C> {
+    /// Draw the BST sideways, one element per line, with the root on the
+    /// left and larger elements above smaller ones:
+    ///
+    /// ```text
+    ///     /-- 3
+    /// 2
+    ///     \-- 1
+    /// ```
+    pub fn to_ascii(&self) -> String {
+        let mut out = String::new();
+        if let Some(ref root) = self.root {
+            draw_sideways(root, "", None, &mut out);
+        }
+        out
+    }
+
+    /// Export the BST as a Graphviz DOT graph.
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with(&[])
+    }
+
+    /// Export the BST as a Graphviz DOT graph, adding the given
+    /// annotations to the label of every node.
+    pub fn to_dot_with(&self, annotations: &[Annotation]) -> String {
+        let mut shapes = vec![];
+        measure(&self.root, &mut shapes);
+        let mut out = String::from("digraph BST {\n");
+        let mut next_id = 0;
+        write_dot(&self.root, annotations, &mut shapes.iter(), &mut next_id, &mut out);
+        out.push_str("}\n");
+        out
+    }
+}
+
+impl<T, C> BST<T, C> {
+    /// Summarize the shape of the BST.
+    pub fn stats(&self) -> Stats {
+        let mut shapes = vec![];
+        let root = measure(&self.root, &mut shapes);
+        let mut stats = Stats {
+            size: root.size,
+            height: root.height,
+            balance: BTreeMap::new()
+        };
+        for shape in &shapes {
+            *stats.balance.entry(shape.balance).or_insert(0) += 1;
+        }
+        stats
+    }
+}
+
+// The size, height and balance factor of a subtree, which `measure` finds
+// for every node in one pass.
+#[derive(Clone, Copy, Debug, Default)]
+struct Shape {
+    size: usize,
+    height: usize,
+    balance: isize,
+}
+
+// Return the shape of a subtree, and push the shape of every node in it
+// onto `shapes` in pre-order, the order `write_dot` visits them in.
+fn measure<T>(link: &Link<T>, shapes: &mut Vec<Shape>) -> Shape {
+    match *link {
+        None => Shape::default(),
+        Some(ref node) => {
+            let index = shapes.len();
+            shapes.push(Shape::default());
+            let left = measure(&node.left, shapes);
+            let right = measure(&node.right, shapes);
+            shapes[index] = Shape {
+                size: 1 + left.size + right.size,
+                height: 1 + cmp::max(left.height, right.height),
+                balance: right.height as isize - left.height as isize
+            };
+            shapes[index]
+        }
+    }
+}
+
+// Draw a node and its subtrees. `side` is the side of its parent the node
+// is on, or None for the root. Lines of the subtrees start with `prefix`,
+// and have a bar where they pass by the line to the parent.
+fn draw_sideways<T: fmt::Display>(node: &Node<T>, prefix: &str, side: Option<Side>, out: &mut String) {
+    if let Some(ref right) = node.right {
+        let bar = if side == Some(Side::Left) { "|   " } else { "    " };
+        draw_sideways(right, &format!("{}{}", prefix, bar), Some(Side::Right), out);
+    }
+    let connector = match side {
+        None => "",
+        Some(Side::Right) => "/-- ",
+        Some(Side::Left) => "\\-- "
+    };
+    out.push_str(&format!("{}{}{}\n", prefix, connector, node.elem));
+    if let Some(ref left) = node.left {
+        let bar = if side == Some(Side::Right) { "|   " } else { "    " };
+        draw_sideways(left, &format!("{}{}", prefix, bar), Some(Side::Left), out);
+    }
+}
+
+// Write the nodes and edges of a subtree, and return the id of its root.
+// `shapes` yields the shapes found by `measure` for the nodes still to be
+// written. A missing child of a node with another child is drawn as a
+// point, so it is clear which side the other child is on.
+fn write_dot<T: fmt::Display>(link: &Link<T>, annotations: &[Annotation], shapes: &mut slice::Iter<Shape>,
+                              next_id: &mut usize, out: &mut String) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+    let node = match *link {
+        None => {
+            out.push_str(&format!("    n{} [shape=point];\n", id));
+            return id;
+        },
+        Some(ref node) => node
+    };
+    let shape = shapes.next().expect("no shape measured for a node");
+    let mut label = node.elem.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+    for annotation in annotations {
+        label.push_str(&match *annotation {
+            Annotation::Height => format!("\\nheight {}", shape.height),
+            Annotation::Size => format!("\\nsize {}", shape.size),
+            Annotation::Balance => format!("\\nbalance {}", shape.balance)
+        });
+    }
+    out.push_str(&format!("    n{} [label=\"{}\"];\n", id, label));
+    if node.left.is_some() || node.right.is_some() {
+        for child in [&node.left, &node.right].iter() {
+            let child_id = write_dot(child, annotations, shapes, next_id, out);
+            out.push_str(&format!("    n{} -> n{};\n", id, child_id));
+        }
+    }
+    id
+}
+
+// ============================================== Split and Join
+
+// Build a perfectly balanced tree out of the first `len` elements of a
+// sorted iterator. The elements are consumed in order, so this takes O(n).
+fn build_balanced<T, I: Iterator<Item = T>>(iter: &mut I, len: usize) -> Link<T> {
+    if len == 0 {
+        return None;
+    }
+    let left_len = len / 2;
+    let left = build_balanced(iter, left_len);
+    let elem = iter.next()?;
+    let right = build_balanced(iter, len - left_len - 1);
+    Some(Box::new(Node {
+        elem,
+        left,
+        right
+    }))
+}
+
+// Split a subtree into the elements less than `elem` and the elements
+// greater than or equal to it. Only the nodes along one path are visited.
+fn split_link<T, C: Compare<T>>(link: Link<T>, elem: &T, cmp: &C) -> (Link<T>, Link<T>) {
+    match link {
+        None => (None, None),
+        Some(mut node) => {
+            if cmp.compare(&node.elem, elem) == Ordering::Less {
+                let (less, rest) = split_link(node.right.take(), elem, cmp);
+                node.right = less;
+                (Some(node), rest)
+            } else {
+                let (less, rest) = split_link(node.left.take(), elem, cmp);
+                node.left = rest;
+                (less, Some(node))
+            }
+        }
+    }
+}
+
+// Detach the node holding the largest element of a subtree, putting its
+// left subtree in its place.
+fn take_max<T>(mut link: &mut Link<T>) -> Link<T> {
+    while link.as_ref().is_some_and(|node| node.right.is_some()) {
+        link = &mut link.as_mut().unwrap().right;
+    }
+    let mut node = link.take()?;
+    *link = node.left.take();
+    Some(node)
+}
+
+impl<T: Ord> BST<T> {
+    /// Build a balanced BST from an iterator which yields elements in
+    /// strictly ascending order. Takes O(n) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the elements are not strictly ascending.
+    pub fn from_sorted_iter<I: IntoIterator<Item = T>>(iter: I) -> BST<T> {
+        BST::from_sorted_iter_with_comparator(iter, Natural)
+    }
+}
+
+impl<T, C: Compare<T>> BST<T, C> {
+    /// Build a balanced BST ordered by `cmp` from an iterator which yields
+    /// elements in strictly ascending order by `cmp`. Takes O(n) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the elements are not strictly ascending.
+    pub fn from_sorted_iter_with_comparator<I: IntoIterator<Item = T>>(iter: I, cmp: C) -> BST<T, C> {
+        let elems: Vec<T> = iter.into_iter().collect();
+        assert!(elems.windows(2).all(|pair| cmp.compare(&pair[0], &pair[1]) == Ordering::Less),
+                "from_sorted_iter requires strictly ascending input");
+        let len = elems.len();
+        BST { root: build_balanced(&mut elems.into_iter(), len), cmp }
+    }
+}
+
+impl<T, C: Compare<T>> BST<T, C> {
+    /// Split the BST at `elem`. Afterwards `self` contains the elements
+    /// less than `elem`, and the returned BST contains the rest.
+    /// Takes time proportional to the height of the tree.
+    pub fn split_off(&mut self, elem: &T) -> BST<T, C> where C: Clone {
+        let (less, rest) = split_link(self.root.take(), elem, &self.cmp);
+        self.root = less;
+        BST { root: rest, cmp: self.cmp.clone() }
+    }
+
+    /// Concatenate `other` onto `self`. Takes time proportional to the
+    /// height of the tree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if some element of `other` is not greater than all the
+    /// elements of `self`.
+    pub fn join(&mut self, other: BST<T, C>) {
+        if let (Some(max), Some(min)) = (self.max(), other.min()) {
+            assert!(self.cmp.compare(max, min) == Ordering::Less,
+                    "join requires every element of `other` to be greater");
+        }
+        match take_max(&mut self.root) {
+            None => self.root = other.root,
+            Some(mut node) => {
+                node.left = self.root.take();
+                node.right = other.root;
+                self.root = Some(node);
+            }
+        }
+    }
+}
+
+// ============================================== Set Operations
+
+// The iterators below merge any two sorted iterators of references, so
+// `ArenaBST` uses them too.
+
+impl<T, C: Compare<T>> BST<T, C> {
+    /// Visit the elements which are in `self` or `other` (or both), in
+    /// ascending order.
+    pub fn union<'a>(&'a self, other: &'a BST<T, C>) -> Union<'a, T, C> {
+        Union::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    /// Visit the elements which are both in `self` and `other`, in
+    /// ascending order.
+    pub fn intersection<'a>(&'a self, other: &'a BST<T, C>) -> Intersection<'a, T, C> {
+        Intersection::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    /// Visit the elements which are in `self` but not in `other`, in
+    /// ascending order.
+    pub fn difference<'a>(&'a self, other: &'a BST<T, C>) -> Difference<'a, T, C> {
+        Difference::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    /// Visit the elements which are in `self` or `other` but not in both,
+    /// in ascending order.
+    pub fn symmetric_difference<'a>(&'a self, other: &'a BST<T, C>) -> SymmetricDifference<'a, T, C> {
+        SymmetricDifference::new(self.iter(), other.iter(), &self.cmp)
+    }
+
+    /// Return true if every element of `self` is also in `other`.
+    /// Takes O(n + m) time.
+    pub fn is_subset(&self, other: &BST<T, C>) -> bool {
+        self.difference(other).next().is_none()
+    }
+
+    /// Return true if `self` and `other` have no elements in common.
+    /// Takes O(n + m) time.
+    pub fn is_disjoint(&self, other: &BST<T, C>) -> bool {
+        self.intersection(other).next().is_none()
+    }
+
+    /// Move all elements of `other` into `self`, leaving `other` empty.
+    /// Elements already in `self` are kept and the duplicates from `other`
+    /// are dropped. The result is rebuilt as a balanced tree in O(n + m).
+    pub fn append(&mut self, other: &mut BST<T, C>) {
+        let mut a = IntoIter::new(self.root.take()).peekable();
+        let mut b = IntoIter::new(other.root.take()).peekable();
+        let mut merged = Vec::new();
+        loop {
+            let order = match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) => self.cmp.compare(x, y),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break
+            };
+            match order {
+                Ordering::Less => merged.extend(a.next()),
+                Ordering::Greater => merged.extend(b.next()),
+                Ordering::Equal => {
+                    b.next();
+                    merged.extend(a.next());
+                }
+            }
+        }
+        let len = merged.len();
+        self.root = build_balanced(&mut merged.into_iter(), len);
+    }
+}
+
+pub struct Union<'a, T: 'a, C: 'a = Natural, I: Iterator<Item = &'a T> = Iter<'a, T>> {
+    a: Peekable<I>,
+    b: Peekable<I>,
+    cmp: &'a C
+}
+
+impl<'a, T: 'a, C: 'a, I: Iterator<Item = &'a T>> Union<'a, T, C, I> {
+    pub(crate) fn new(a: I, b: I, cmp: &'a C) -> Self {
+        Union {
+            a: a.peekable(),
+            b: b.peekable(),
+            cmp
+        }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T> + 'a, I: Iterator<Item = &'a T>> Iterator for Union<'a, T, C, I> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match (self.a.peek().cloned(), self.b.peek().cloned()) {
+            (Some(x), Some(y)) => match self.cmp.compare(x, y) {
+                Ordering::Less => self.a.next(),
+                Ordering::Greater => self.b.next(),
+                Ordering::Equal => {
+                    self.b.next();
+                    self.a.next()
+                }
+            },
+            (Some(_), None) => self.a.next(),
+            (None, _) => self.b.next()
+        }
+    }
+}
+
+pub struct Intersection<'a, T: 'a, C: 'a = Natural, I: Iterator<Item = &'a T> = Iter<'a, T>> {
+    a: Peekable<I>,
+    b: Peekable<I>,
+    cmp: &'a C
+}
+
+impl<'a, T: 'a, C: 'a, I: Iterator<Item = &'a T>> Intersection<'a, T, C, I> {
+    pub(crate) fn new(a: I, b: I, cmp: &'a C) -> Self {
+        Intersection {
+            a: a.peekable(),
+            b: b.peekable(),
+            cmp
+        }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T> + 'a, I: Iterator<Item = &'a T>> Iterator for Intersection<'a, T, C, I> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match (self.a.peek().cloned(), self.b.peek().cloned()) {
+                (Some(x), Some(y)) => match self.cmp.compare(x, y) {
+                    Ordering::Less => {
+                        self.a.next();
+                    },
+                    Ordering::Greater => {
+                        self.b.next();
+                    },
+                    Ordering::Equal => {
+                        self.b.next();
+                        return self.a.next();
+                    }
+                },
+                _ => return None
+            }
+        }
+    }
+}
+
+pub struct Difference<'a, T: 'a, C: 'a = Natural, I: Iterator<Item = &'a T> = Iter<'a, T>> {
+    a: Peekable<I>,
+    b: Peekable<I>,
+    cmp: &'a C
+}
+
+impl<'a, T: 'a, C: 'a, I: Iterator<Item = &'a T>> Difference<'a, T, C, I> {
+    pub(crate) fn new(a: I, b: I, cmp: &'a C) -> Self {
+        Difference {
+            a: a.peekable(),
+            b: b.peekable(),
+            cmp
+        }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T> + 'a, I: Iterator<Item = &'a T>> Iterator for Difference<'a, T, C, I> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match (self.a.peek().cloned(), self.b.peek().cloned()) {
+                (Some(x), Some(y)) => match self.cmp.compare(x, y) {
+                    Ordering::Less => return self.a.next(),
+                    Ordering::Greater => {
+                        self.b.next();
+                    },
+                    Ordering::Equal => {
+                        self.a.next();
+                        self.b.next();
+                    }
+                },
+                _ => return self.a.next()
+            }
+        }
+    }
+}
+
+pub struct SymmetricDifference<'a, T: 'a, C: 'a = Natural, I: Iterator<Item = &'a T> = Iter<'a, T>> {
+    a: Peekable<I>,
+    b: Peekable<I>,
+    cmp: &'a C
+}
+
+impl<'a, T: 'a, C: 'a, I: Iterator<Item = &'a T>> SymmetricDifference<'a, T, C, I> {
+    pub(crate) fn new(a: I, b: I, cmp: &'a C) -> Self {
+        SymmetricDifference {
+            a: a.peekable(),
+            b: b.peekable(),
+            cmp
+        }
+    }
+}
+
+impl<'a, T: 'a, C: Compare<T> + 'a, I: Iterator<Item = &'a T>> Iterator for SymmetricDifference<'a, T, C, I> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match (self.a.peek().cloned(), self.b.peek().cloned()) {
+                (Some(x), Some(y)) => match self.cmp.compare(x, y) {
+                    Ordering::Less => return self.a.next(),
+                    Ordering::Greater => return self.b.next(),
+                    Ordering::Equal => {
+                        self.a.next();
+                        self.b.next();
+                    }
+                },
+                (Some(_), None) => return self.a.next(),
+                (None, _) => return self.b.next()
+            }
+        }
+    }
+}
+
+// ============================================== Mutation
+
+// Find the link which holds the element `locate` reports as Equal, or the
+// empty link where such an element would be inserted. `locate` tells how
+// the wanted element compares to the element of the node it is given.
+fn find_link<T, F: Fn(&T) -> Ordering>(mut link: &mut Link<T>, locate: F) -> &mut Link<T> {
+    loop {
+        let order = match *link {
+            None => return link,
+            Some(ref node) => locate(&node.elem)
+        };
+        link = match order {
+            Ordering::Less => &mut link.as_mut().unwrap().left,
+            Ordering::Greater => &mut link.as_mut().unwrap().right,
+            Ordering::Equal => return link
+        };
+    }
+}
+
+impl<T, C: Compare<T>> BST<T, C> {
+    /// Keep only the elements for which `f` returns true. The remaining
+    /// elements are rebuilt into a balanced tree in O(n).
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        let kept: Vec<T> = IntoIter::new(self.root.take()).filter(|elem| f(elem)).collect();
+        let len = kept.len();
+        self.root = build_balanced(&mut kept.into_iter(), len);
+    }
+
+    /// Replace the element `old` with `new`, moving it to where `new`
+    /// belongs in the BST. Return the old element, or None if `old` was
+    /// not in the BST or another element equal to `new` was, in which case
+    /// nothing is changed.
+    pub fn replace<Q: ?Sized>(&mut self, old: &Q, new: T) -> Option<T> where T: Borrow<Q>, C: Compare<Q> {
+        let key: &Q = new.borrow();
+        if self.cmp.compare(key, old) != Ordering::Equal && self.search(key) {
+            return None;
+        }
+        let old = self.take(old)?;
+        self.insert(new);
+        Some(old)
+    }
+
+    /// Check that every element is ordered after the previous one, which
+    /// is what all the BST operations rely on. Takes O(n) time.
+    pub fn validate(&self) -> bool {
+        let mut iter = self.iter();
+        let mut prev = match iter.next() {
+            None => return true,
+            Some(elem) => elem
+        };
+        for elem in iter {
+            if self.cmp.compare(prev, elem) != Ordering::Less {
+                return false;
+            }
+            prev = elem;
+        }
+        true
+    }
+}
+
+// ============================================== Map
+
+/// A map from keys to values, stored as a BST ordered by the keys.
+///
+/// Unlike the elements of a `BST`, the values can be changed in place,
+/// while the keys can never be mutated.
+#[derive(Clone)]
+pub struct BSTMap<K, V, C = Natural> {
+    tree: BST<(K, V), ByKey<C>>,
+}
+
+// Order the entries of a `BSTMap` by their keys only.
+#[derive(Clone, Debug)]
+struct ByKey<C>(C);
+
+impl<K, V, C: Compare<K>> Compare<(K, V)> for ByKey<C> {
+    fn compare(&self, l: &(K, V), r: &(K, V)) -> Ordering {
+        self.0.compare(&l.0, &r.0)
+    }
+}
+
+impl<K, V, C: Compare<K> + Default> Default for BSTMap<K, V, C> {
+    fn default() -> Self {
+        BSTMap::with_comparator(C::default())
+    }
+}
+
+impl<K: Ord, V> BSTMap<K, V> {
+    pub fn new() -> BSTMap<K, V> {
+        BSTMap::with_comparator(Natural)
+    }
+}
+
+impl<K, V, C: Compare<K>> BSTMap<K, V, C> {
+    /// Create an empty map whose keys are ordered by `cmp`.
+    pub fn with_comparator(cmp: C) -> BSTMap<K, V, C> {
+        BSTMap { tree: BST::with_comparator(ByKey(cmp)) }
+    }
+
+    /// Insert a key-value pair into the map. If the key was already in the
+    /// map, its value is replaced and the old value is returned.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let cmp = &self.tree.cmp.0;
+        let link = find_link(&mut self.tree.root, |entry| cmp.compare(&key, &entry.0));
+        if let Some(ref mut node) = *link {
+            return Some(mem::replace(&mut node.elem.1, value));
+        }
+        *link = Some(Box::new(Node {
+            elem: (key, value),
+            left: None,
+            right: None
+        }));
+        None
+    }
+
+    /// Return a reference to the value of `key`, or None if the key is not
+    /// in the map.
+    pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V> where K: Borrow<Q>, C: Compare<Q> {
+        let mut cur = self.tree.root.as_ref();
+        while let Some(node) = cur {
+            match self.tree.cmp.0.compare(key, node.elem.0.borrow()) {
+                Ordering::Less => cur = node.left.as_ref(),
+                Ordering::Greater => cur = node.right.as_ref(),
+                Ordering::Equal => return Some(&node.elem.1)
+            }
+        }
+        None
+    }
+
+    /// Return a mutable reference to the value of `key`, or None if the key
+    /// is not in the map.
+    pub fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut V> where K: Borrow<Q>, C: Compare<Q> {
+        let cmp = &self.tree.cmp.0;
+        find_link(&mut self.tree.root, |entry| cmp.compare(key, entry.0.borrow()))
+            .as_mut()
+            .map(|node| &mut node.elem.1)
+    }
+
+    /// Return true if `key` is in the map.
+    pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool where K: Borrow<Q>, C: Compare<Q> {
+        self.get(key).is_some()
+    }
+
+    /// Remove `key` from the map and return its value, or None if the key
+    /// was not in the map.
+    pub fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<V> where K: Borrow<Q>, C: Compare<Q> {
+        let cmp = &self.tree.cmp.0;
+        unlink(find_link(&mut self.tree.root, |entry| cmp.compare(key, entry.0.borrow())))
+            .map(|node| node.elem.1)
+    }
+
+    /// Keep only the entries for which `f` returns true.
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) {
+        let kept: Vec<(K, V)> = IntoIter::new(self.tree.root.take())
+            .filter_map(|(key, mut value)| if f(&key, &mut value) { Some((key, value)) } else { None })
+            .collect();
+        let len = kept.len();
+        self.tree.root = build_balanced(&mut kept.into_iter(), len);
+    }
+
+    /// Return the number of entries in the map. Like `BST::len`, this
+    /// takes time proportional to it.
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    /// Return true if the map has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Remove all the entries.
+    pub fn clear(&mut self) {
+        self.tree.clear();
+    }
+
+    /// Visit the entries in ascending order of their keys.
+    pub fn iter(&self) -> MapIter<'_, K, V> {
+        MapIter(self.tree.iter())
+    }
+
+    /// Visit the entries in ascending order of their keys, with mutable
+    /// references to the values.
+    pub fn iter_mut(&mut self) -> MapIterMut<'_, K, V> {
+        MapIterMut(self.tree.iter_mut())
+    }
+}
+
+pub struct MapIter<'a, K: 'a, V: 'a>(Iter<'a, (K, V)>);
+
+impl<'a, K: 'a, V: 'a> Iterator for MapIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|entry| (&entry.0, &entry.1))
+    }
+}
+
+pub struct MapIterMut<'a, K: 'a, V: 'a>(IterMut<'a, (K, V)>);
+
+impl<'a, K: 'a, V: 'a> Iterator for MapIterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|entry| (&entry.0, &mut entry.1))
+    }
+}
+
+// ============================================== Traits
+
+impl<T: fmt::Debug, C> fmt::Debug for BST<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, C> PartialEq for BST<T, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, C> Eq for BST<T, C> {}
+
+impl<T: Hash, C> Hash for BST<T, C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut len = 0;
+        for elem in self {
+            elem.hash(state);
+            len += 1;
+        }
+        state.write_usize(len);
+    }
+}
+
+impl<T, C: Compare<T> + Default> FromIterator<T> for BST<T, C> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tree = BST::default();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<T, C: Compare<T>> Extend<T> for BST<T, C> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.insert(elem);
+        }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, C> fmt::Debug for BSTMap<K, V, C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.tree.iter().map(|entry| (&entry.0, &entry.1))).finish()
+    }
+}
+
+impl<K: PartialEq, V: PartialEq, C> PartialEq for BSTMap<K, V, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.tree == other.tree
+    }
+}
+
+impl<K: Eq, V: Eq, C> Eq for BSTMap<K, V, C> {}
+
+impl<K: Hash, V: Hash, C> Hash for BSTMap<K, V, C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.tree.hash(state);
+    }
+}
+
+impl<K, V, C: Compare<K> + Default> FromIterator<(K, V)> for BSTMap<K, V, C> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = BSTMap::default();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K, V, C: Compare<K>> Extend<(K, V)> for BSTMap<K, V, C> {
+    /// Insert every pair, so later values replace earlier ones of the same
+    /// key.
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+// ============================================== Serde
+
+// A BST is serialized as the sequence of its elements in order, and is
+// rebuilt balanced when deserialized.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use std::cmp::Ordering;
+    use std::fmt;
+    use std::marker::PhantomData;
+
+    use serde::de::{Deserialize, Deserializer, Error, SeqAccess, Visitor};
+    use serde::ser::{Serialize, Serializer};
+
+    use compare::Compare;
+    use super::{build_balanced, BST};
+
+    impl<T: Serialize, C> Serialize for BST<T, C> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self)
+        }
+    }
+
+    impl<'de, T: Deserialize<'de>, C: Compare<T> + Default> Deserialize<'de> for BST<T, C> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_seq(BSTVisitor(PhantomData))
+        }
+    }
+
+    struct BSTVisitor<T, C>(PhantomData<fn() -> BST<T, C>>);
+
+    impl<'de, T: Deserialize<'de>, C: Compare<T> + Default> Visitor<'de> for BSTVisitor<T, C> {
+        type Value = BST<T, C>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a sorted sequence of unique elements")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let cmp = C::default();
+            // Do not trust the size hint too far, it comes from the input.
+            let mut elems: Vec<T> = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+            while let Some(elem) = seq.next_element()? {
+                if let Some(last) = elems.last() {
+                    match cmp.compare(last, &elem) {
+                        Ordering::Less => {},
+                        Ordering::Equal => return Err(A::Error::custom("duplicate element")),
+                        Ordering::Greater => return Err(A::Error::custom("elements are not sorted"))
+                    }
+                }
+                elems.push(elem);
+            }
+            let len = elems.len();
+            Ok(BST { root: build_balanced(&mut elems.into_iter(), len), cmp })
+        }
+    }
+}
+
+// ============================================== Test
+
+#[cfg(test)]
+mod test {
+    use super::{Annotation, BST, BSTMap};
+    use compare::{Natural, Reverse};
+    use std::collections::HashSet;
+    use std::ops::Bound;
+
+    fn tree_of(elems: &[i32]) -> BST<i32> {
+        let mut tree = BST::new();
+        for elem in elems {
+            tree.insert(*elem);
+        }
+        tree
+    }
+
+    #[test]
+    fn basics() {
+        let mut tree = BST::new();
+
+        // Check empty list behaves right
+        assert_eq!(tree.search(&1), false);
+
+        // Populate list
+        assert_eq!(tree.insert(1), true);
+        assert_eq!(tree.insert(2), true);
+        assert_eq!(tree.insert(3), true);
+
+        // Check search
+        assert_eq!(tree.search(&1), true);
+        assert_eq!(tree.search(&2), true);
+        assert_eq!(tree.search(&3), true);
+        assert_eq!(tree.search(&4), false);
+
+        // Check repeatly insertion
+        assert_eq!(tree.insert(1), false);
+    }
+
+    #[test]
+    fn test_min_max() {
+        let mut tree = BST::new();
+
+        // Check empty tree behaves right
+        assert_eq!(tree.min(), None);
+        assert_eq!(tree.max(), None);
+
+        // Populate tree
+        for elem in &[5, 3, 8, 1, 4, 9] {
+            tree.insert(*elem);
+        }
+
+        assert_eq!(tree.min(), Some(&1));
+        assert_eq!(tree.max(), Some(&9));
+    }
+
+    #[test]
+    fn test_floor_ceiling() {
+        let mut tree = BST::new();
+
+        // Check empty tree behaves right
+        assert_eq!(tree.floor(&1), None);
+        assert_eq!(tree.ceiling(&1), None);
+
+        // Populate tree
+        for elem in &[10, 20, 30, 40] {
+            tree.insert(*elem);
+        }
+
+        // Check exact matches
+        assert_eq!(tree.floor(&20), Some(&20));
+        assert_eq!(tree.ceiling(&20), Some(&20));
+
+        // Check elements between the stored ones
+        assert_eq!(tree.floor(&25), Some(&20));
+        assert_eq!(tree.ceiling(&25), Some(&30));
+
+        // Check elements outside the stored range
+        assert_eq!(tree.floor(&5), None);
+        assert_eq!(tree.ceiling(&5), Some(&10));
+        assert_eq!(tree.floor(&45), Some(&40));
+        assert_eq!(tree.ceiling(&45), None);
+    }
+
+    #[test]
+    fn test_predecessor_successor() {
+        let mut tree = BST::new();
+
+        // Check empty tree behaves right
+        assert_eq!(tree.predecessor(&1), None);
+        assert_eq!(tree.successor(&1), None);
+
+        // Populate tree
+        for elem in &[10, 20, 30, 40] {
+            tree.insert(*elem);
+        }
+
+        // Check elements in the tree
+        assert_eq!(tree.predecessor(&20), Some(&10));
+        assert_eq!(tree.successor(&20), Some(&30));
+        assert_eq!(tree.predecessor(&10), None);
+        assert_eq!(tree.successor(&40), None);
+
+        // Check elements not in the tree
+        assert_eq!(tree.predecessor(&25), Some(&20));
+        assert_eq!(tree.successor(&25), Some(&30));
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let mut tree = BST::new();
+
+        // Populate list
+        assert_eq!(tree.insert(1), true);
+        assert_eq!(tree.insert(2), true);
+        assert_eq!(tree.insert(3), true);
+
+        // Check into_iterator
+        let mut iter = tree.into_iter();
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.next(), Some(2));
+        assert_eq!(iter.next(), Some(3));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut tree = BST::new();
+
+        // Populate list
+        assert_eq!(tree.insert(1), true);
+        assert_eq!(tree.insert(2), true);
+        assert_eq!(tree.insert(3), true);
+
+        // Check into_iterator
+        {
+            let mut iter = (&tree).into_iter();
+            assert_eq!(iter.next(), Some(&1));
+            assert_eq!(iter.next(), Some(&2));
+            assert_eq!(iter.next(), Some(&3));
+            assert_eq!(iter.next(), None);
+        }
+
+        // Check repeatly insertion
+        assert_eq!(tree.insert(1), false);
+    }
+
+    #[test]
+    fn test_traversals() {
+        //       4
+        //     /   \
+        //    2     6
+        //   / \     \
+        //  1   3     7
+        let tree = tree_of(&[4, 2, 6, 1, 3, 7]);
+
+        assert_eq!(tree.iter_preorder().cloned().collect::<Vec<_>>(), vec![4, 2, 1, 3, 6, 7]);
+        assert_eq!(tree.iter_postorder().cloned().collect::<Vec<_>>(), vec![1, 3, 2, 7, 6, 4]);
+        assert_eq!(tree.iter_level_order().cloned().collect::<Vec<_>>(), vec![4, 2, 6, 1, 3, 7]);
+        assert_eq!(tree.iter_level_order().with_depth().map(|(depth, elem)| (depth, *elem)).collect::<Vec<_>>(),
+                   vec![(0, 4), (1, 2), (1, 6), (2, 1), (2, 3), (2, 7)]);
+
+        // Rebuilding from the pre-order keeps the shape
+        let copy = tree_of(&tree.iter_preorder().cloned().collect::<Vec<_>>());
+        assert_eq!(copy.iter_level_order().cloned().collect::<Vec<_>>(), vec![4, 2, 6, 1, 3, 7]);
+
+        // Check empty tree behaves right
+        let empty: BST<i32> = BST::new();
+        assert_eq!(empty.iter_preorder().next(), None);
+        assert_eq!(empty.iter_postorder().next(), None);
+        assert_eq!(empty.iter_level_order().next(), None);
+    }
+
+    #[test]
+    fn test_map_iter_mut() {
+        let mut map = BSTMap::new();
+        map.insert(1, "one".to_string());
+        map.insert(2, "two".to_string());
+        map.insert(3, "three".to_string());
+
+        // Only the values can be changed through the iterator
+        for (key, value) in map.iter_mut() {
+            if *key == 3 {
+                value.push('!');
+            }
+        }
+
+        assert_eq!(map.get(&3).map(|v| v.as_str()), Some("three!"));
+        assert_eq!(map.iter().map(|(k, _)| *k).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(map.tree.validate(), true);
+    }
+
+    #[test]
+    fn test_iter_order() {
+        // A shape where nodes have both children
+        let mut tree = tree_of(&[4, 2, 6, 1, 3, 5, 7]);
+
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(tree.iter_mut().map(|e| *e).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(tree.into_iter().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = tree_of(&[5, 1, 3, 7, 9]);
+        let b = tree_of(&[4, 3, 9, 10]);
+
+        assert_eq!(a.union(&b).cloned().collect::<Vec<_>>(), vec![1, 3, 4, 5, 7, 9, 10]);
+        assert_eq!(a.intersection(&b).cloned().collect::<Vec<_>>(), vec![3, 9]);
+        assert_eq!(a.difference(&b).cloned().collect::<Vec<_>>(), vec![1, 5, 7]);
+        assert_eq!(b.difference(&a).cloned().collect::<Vec<_>>(), vec![4, 10]);
+        assert_eq!(a.symmetric_difference(&b).cloned().collect::<Vec<_>>(), vec![1, 4, 5, 7, 10]);
+
+        // Check empty trees behave right
+        let empty = BST::new();
+        assert_eq!(a.union(&empty).count(), 5);
+        assert_eq!(a.intersection(&empty).count(), 0);
+        assert_eq!(empty.difference(&a).count(), 0);
+        assert_eq!(empty.symmetric_difference(&a).count(), 5);
+    }
+
+    #[test]
+    fn test_subset_disjoint() {
+        let a = tree_of(&[2, 1, 3]);
+        let b = tree_of(&[3, 1, 2, 4]);
+        let c = tree_of(&[5, 6]);
+
+        assert_eq!(a.is_subset(&b), true);
+        assert_eq!(b.is_subset(&a), false);
+        assert_eq!(BST::new().is_subset(&a), true);
+
+        assert_eq!(a.is_disjoint(&c), true);
+        assert_eq!(a.is_disjoint(&b), false);
+    }
+
+    #[test]
+    fn test_append() {
+        let mut a = tree_of(&[1, 3, 5]);
+        let mut b = tree_of(&[2, 3, 4, 6]);
+
+        a.append(&mut b);
+        assert_eq!(a.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(b.iter().next(), None);
+
+        // The merged tree should still work as a BST
+        assert_eq!(a.search(&4), true);
+        assert_eq!(a.insert(4), false);
+        assert_eq!(a.insert(7), true);
+    }
+
+    #[test]
+    fn test_from_sorted_iter() {
+        let mut tree = BST::from_sorted_iter(1..8);
+
+        // Check the shape is perfectly balanced
+        assert_eq!(tree.root.as_ref().map(|node| node.elem), Some(4));
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6, 7]);
+
+        // Check it still works as a BST
+        assert_eq!(tree.search(&6), true);
+        assert_eq!(tree.insert(6), false);
+        assert_eq!(tree.insert(8), true);
+
+        // Check empty input behaves right
+        let empty = BST::from_sorted_iter(Vec::<i32>::new());
+        assert_eq!(empty.min(), None);
+
+        // Check a custom comparator, which orders the input descending
+        let mut tree = BST::from_sorted_iter_with_comparator(vec![3, 2, 1], Reverse(Natural));
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![3, 2, 1]);
+        assert_eq!(tree.insert(0), true);
+        assert_eq!(tree.max(), Some(&0));
+    }
+
+    #[test]
+    #[should_panic(expected = "from_sorted_iter requires strictly ascending input")]
+    fn test_from_sorted_iter_unsorted() {
+        BST::from_sorted_iter(vec![1, 3, 2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "from_sorted_iter requires strictly ascending input")]
+    fn test_from_sorted_iter_duplicates() {
+        BST::from_sorted_iter_with_comparator(vec![2, 2], Reverse(Natural));
+    }
+
+    #[test]
+    fn test_split_off() {
+        let mut tree = tree_of(&[4, 2, 6, 1, 3, 5, 7]);
+
+        let rest = tree.split_off(&4);
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(rest.iter().cloned().collect::<Vec<_>>(), vec![4, 5, 6, 7]);
+
+        // Split at an element which is not in the tree
+        let mut tree = tree_of(&[4, 2, 6, 1, 3, 5, 7]);
+        let rest = tree.split_off(&10);
+        assert_eq!(tree.iter().count(), 7);
+        assert_eq!(rest.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_join() {
+        let mut tree = tree_of(&[2, 1, 3]);
+        tree.join(tree_of(&[5, 4, 6]));
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(tree.search(&5), true);
+
+        // Check joining with empty trees
+        let mut tree = BST::new();
+        tree.join(tree_of(&[1, 2]));
+        tree.join(BST::new());
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_join_overlapping() {
+        let mut tree = tree_of(&[1, 5]);
+        tree.join(tree_of(&[3, 7]));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut tree = tree_of(&[4, 2, 6, 1, 3, 5, 7]);
+
+        // Remove a leaf, a node with one child and a node with two children
+        assert_eq!(tree.remove(&1), true);
+        assert_eq!(tree.remove(&2), true);
+        assert_eq!(tree.remove(&4), true);
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![3, 5, 6, 7]);
+
+        // Check removing missing elements
+        assert_eq!(tree.remove(&4), false);
+        assert_eq!(tree.take(&5), Some(5));
+        assert_eq!(tree.take(&5), None);
+
+        // Check the tree is not corrupted
+        assert_eq!(tree.search(&3), true);
+        assert_eq!(tree.insert(4), true);
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![3, 4, 6, 7]);
+    }
+
+    #[test]
+    fn test_borrowed_lookup() {
+        let mut tree = BST::new();
+        tree.insert("banana".to_string());
+        tree.insert("apple".to_string());
+        tree.insert("cherry".to_string());
+
+        // Look up with &str without allocating a String
+        assert_eq!(tree.search("apple"), true);
+        assert_eq!(tree.search("durian"), false);
+        assert_eq!(tree.floor("bz").map(|s| s.as_str()), Some("banana"));
+        assert_eq!(tree.ceiling("bz").map(|s| s.as_str()), Some("cherry"));
+        assert_eq!(tree.remove("banana"), true);
+        assert_eq!(tree.range::<str, _>((Bound::Included("a"), Bound::Excluded("c"))).count(), 1);
+    }
+
+    #[test]
+    fn test_range() {
+        let tree = tree_of(&[4, 2, 6, 1, 3, 5, 7]);
+
+        assert_eq!(tree.range(2..5).cloned().collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(tree.range(2..=5).cloned().collect::<Vec<_>>(), vec![2, 3, 4, 5]);
+        assert_eq!(tree.range(..3).cloned().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(tree.range(6..).cloned().collect::<Vec<_>>(), vec![6, 7]);
+        assert_eq!(tree.range(..).count(), 7);
+
+        // Check ranges with bounds between or outside the elements
+        assert_eq!(tree.range(0..100).count(), 7);
+        assert_eq!(tree.range(8..).count(), 0);
+        assert_eq!(tree.range(5..5).count(), 0);
+        assert_eq!(tree.range((Bound::Excluded(3), Bound::Excluded(5))).cloned().collect::<Vec<_>>(), vec![4]);
+    }
+
+    #[test]
+    fn test_reverse_comparator() {
+        let mut tree = BST::with_comparator(Reverse(Natural));
+        for elem in &[3, 1, 4, 5, 2] {
+            tree.insert(*elem);
+        }
+
+        // Check everything follows the descending order
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![5, 4, 3, 2, 1]);
+        assert_eq!(tree.min(), Some(&5));
+        assert_eq!(tree.floor(&0), Some(&1));
+        assert_eq!(tree.successor(&3), Some(&2));
+        assert_eq!(tree.range((Bound::Included(4), Bound::Included(2))).cloned().collect::<Vec<_>>(), vec![4, 3, 2]);
+
+        let rest = tree.split_off(&3);
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![5, 4]);
+        assert_eq!(rest.iter().cloned().collect::<Vec<_>>(), vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_closure_comparator() {
+        let case_insensitive = |a: &String, b: &String| a.to_lowercase().cmp(&b.to_lowercase());
+        let mut tree = BST::with_comparator(case_insensitive);
+
+        assert_eq!(tree.insert("Banana".to_string()), true);
+        assert_eq!(tree.insert("apple".to_string()), true);
+        assert_eq!(tree.insert("APPLE".to_string()), false);
+
+        assert_eq!(tree.search(&"BANANA".to_string()), true);
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec!["apple", "Banana"]);
+
+        // Set operations use the same comparator
+        let mut other = BST::with_comparator(case_insensitive);
+        other.insert("BANANA".to_string());
+        assert_eq!(tree.intersection(&other).count(), 1);
+        assert_eq!(tree.is_subset(&other), false);
+    }
+
+    #[test]
+    fn test_comparator_by_key() {
+        let by_abs = |a: &i32, b: &i32| a.abs().cmp(&b.abs()).then(a.cmp(b));
+        let mut tree = BST::with_comparator(by_abs);
+        for elem in &[-3, 2, -1, 4] {
+            tree.insert(*elem);
+        }
+
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![-1, 2, -3, 4]);
+        assert_eq!(tree.remove(&2), true);
+        assert_eq!(tree.max(), Some(&4));
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut tree = tree_of(&[4, 2, 6, 1, 3, 5, 7]);
+
+        tree.retain(|elem| elem % 2 == 1);
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![1, 3, 5, 7]);
+        assert_eq!(tree.validate(), true);
+        assert_eq!(tree.search(&2), false);
+        assert_eq!(tree.insert(2), true);
+    }
+
+    #[test]
+    fn test_replace() {
+        let mut tree = tree_of(&[1, 2, 3]);
+
+        // Move 3 after 4 was inserted, unlike changing it through a reference
+        assert_eq!(tree.replace(&3, 4), Some(3));
+        assert_eq!(tree.validate(), true);
+        assert_eq!(tree.insert(3), true);
+        assert_eq!(tree.insert(4), false);
+
+        // Move an element to the other side of the root
+        assert_eq!(tree.replace(&1, 10), Some(1));
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![2, 3, 4, 10]);
+
+        // Check replacing a missing element changes nothing
+        assert_eq!(tree.replace(&7, 8), None);
+        assert_eq!(tree.search(&8), false);
+
+        // Check colliding with another element changes nothing either
+        assert_eq!(tree.replace(&2, 3), None);
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![2, 3, 4, 10]);
+        assert_eq!(tree.replace(&4, 4), Some(4));
+        assert_eq!(tree.len(), 4);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut tree = tree_of(&[2, 1, 3]);
+        assert_eq!(tree.validate(), true);
+        assert_eq!(BST::<i32>::new().validate(), true);
+
+        // Break the ordering on purpose
+        for elem in tree.iter_mut() {
+            if *elem == 3 {
+                *elem = 0;
+            }
+        }
+        assert_eq!(tree.validate(), false);
+    }
+
+    #[test]
+    fn test_cursor() {
+        let tree = tree_of(&[4, 2, 6, 1, 3, 5, 7]);
+
+        // Walk forward from the front and past the end
+        let mut cursor = tree.cursor_front();
+        let mut seen = vec![];
+        while let Some(elem) = cursor.current() {
+            seen.push(*elem);
+            cursor.move_next();
+        }
+        assert_eq!(seen, vec![1, 2, 3, 4, 5, 6, 7]);
+
+        // The ghost position wraps around
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some(&1));
+        cursor.move_prev();
+        assert_eq!(cursor.current(), None);
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some(&7));
+
+        let cursor = tree.cursor_at(&4);
+        assert_eq!(cursor.peek_prev(), Some(&3));
+        assert_eq!(cursor.current(), Some(&4));
+        assert_eq!(cursor.peek_next(), Some(&5));
+
+        // Missing elements put the cursor at the next one
+        assert_eq!(tree.cursor_at(&0).current(), Some(&1));
+        assert_eq!(tree.cursor_at(&8).current(), None);
+        assert_eq!(tree_of(&[2, 4]).cursor_at(&3).current(), Some(&4));
+
+        // Check empty tree behaves right
+        let empty: BST<i32> = BST::new();
+        let mut cursor = empty.cursor_back();
+        assert_eq!(cursor.current(), None);
+        cursor.move_next();
+        assert_eq!(cursor.peek_prev(), None);
+    }
+
+    #[test]
+    fn test_cursor_mut() {
+        let mut tree = tree_of(&[4, 2, 6, 1, 3, 5, 7]);
+        {
+            let mut cursor = tree.cursor_at_mut(&2);
+            assert_eq!(cursor.peek_next(), Some(&3));
+
+            // Removing moves to the next element, whatever children the
+            // removed node had
+            assert_eq!(cursor.remove_current(), Some(2));
+            assert_eq!(cursor.current(), Some(&3));
+            assert_eq!(cursor.remove_current(), Some(3));
+            assert_eq!(cursor.current(), Some(&4));
+            assert_eq!(cursor.remove_current(), Some(4));
+            assert_eq!(cursor.current(), Some(&5));
+            cursor.move_prev();
+            assert_eq!(cursor.current(), Some(&1));
+        }
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![1, 5, 6, 7]);
+
+        {
+            let mut cursor = tree.cursor_back_mut();
+            assert_eq!(cursor.remove_current(), Some(7));
+            assert_eq!(cursor.current(), None);
+            assert_eq!(cursor.remove_current(), None);
+
+            // At the ghost position, insert at either end
+            cursor.insert_after(0);
+            cursor.insert_before(8);
+            cursor.move_prev();
+            assert_eq!(cursor.current(), Some(&8));
+            cursor.insert_before(7);
+            assert_eq!(cursor.peek_prev(), Some(&7));
+        }
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![0, 1, 5, 6, 7, 8]);
+
+        {
+            let mut cursor = tree.cursor_at_mut(&1);
+            cursor.insert_after(3);
+            assert_eq!(cursor.current(), Some(&1));
+            cursor.move_next();
+            assert_eq!(cursor.current(), Some(&3));
+        }
+        assert_eq!(tree.validate(), true);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_cursor_insert_out_of_order() {
+        let mut tree = tree_of(&[1, 5]);
+        tree.cursor_front_mut().insert_after(7);
+    }
+
+    #[test]
+    fn test_to_ascii() {
+        let tree = tree_of(&[4, 2, 6, 1, 3, 7]);
+        assert_eq!(tree.to_ascii(), concat!(
+            "        /-- 7\n",
+            "    /-- 6\n",
+            "4\n",
+            "    |   /-- 3\n",
+            "    \\-- 2\n",
+            "        \\-- 1\n"));
+        assert_eq!(BST::<i32>::new().to_ascii(), "");
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let tree = tree_of(&[2, 3]);
+        assert_eq!(tree.to_dot(), concat!(
+            "digraph BST {\n",
+            "    n0 [label=\"2\"];\n",
+            "    n1 [shape=point];\n",
+            "    n0 -> n1;\n",
+            "    n2 [label=\"3\"];\n",
+            "    n0 -> n2;\n",
+            "}\n"));
+
+        let dot = tree.to_dot_with(&[Annotation::Height, Annotation::Size, Annotation::Balance]);
+        assert!(dot.contains("n0 [label=\"2\\nheight 2\\nsize 2\\nbalance 1\"];"));
+        assert!(dot.contains("n2 [label=\"3\\nheight 1\\nsize 1\\nbalance 0\"];"));
+
+        // Quotes in elements are escaped
+        let mut tree = BST::new();
+        tree.insert("say \"hi\"");
+        assert!(tree.to_dot().contains(r#"[label="say \"hi\""]"#));
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = tree_of(&[4, 2, 6, 1, 3, 7, 8]).stats();
+        assert_eq!(stats.size, 7);
+        assert_eq!(stats.height, 4);
+        assert_eq!(stats.balance.into_iter().collect::<Vec<_>>(), vec![(0, 4), (1, 2), (2, 1)]);
+
+        let stats = tree_of(&[1, 2]).stats();
+        assert_eq!(stats.to_string(), "size 2, height 2, balance factors [0: 1] [1: 1]");
+        assert_eq!(BST::<i32>::new().stats(), Default::default());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let tree = tree_of(&[4, 2, 6, 1, 3, 5, 7]);
+        assert_eq!(serde_json::to_string(&tree).unwrap(), "[1,2,3,4,5,6,7]");
+
+        // Deserializing rebuilds a balanced tree, even from a degenerate one
+        let line = tree_of(&[1, 2, 3, 4, 5, 6, 7]);
+        let tree: BST<i32> = serde_json::from_str(&serde_json::to_string(&line).unwrap()).unwrap();
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(tree.stats().height, 3);
+
+        // The order is checked with the tree's comparator
+        let reversed: BST<i32, Reverse<Natural>> = serde_json::from_str("[3,2,1]").unwrap();
+        assert_eq!(reversed.iter().cloned().collect::<Vec<_>>(), vec![3, 2, 1]);
+
+        assert!(serde_json::from_str::<BST<i32>>("[1,3,2]").is_err());
+        assert!(serde_json::from_str::<BST<i32>>("[1,1]").is_err());
+        assert!(serde_json::from_str::<BST<i32>>("[]").unwrap().root.is_none());
+    }
+
+    #[test]
+    fn test_traits() {
+        let tree: BST<i32> = vec![3, 1, 2].into_iter().collect();
+        assert_eq!(format!("{:?}", tree), "{1, 2, 3}");
+        assert_eq!(tree.len(), 3);
+
+        // Equal elements make equal trees, whatever their shapes
+        let mut copy = tree.clone();
+        assert_eq!(copy, tree_of(&[1, 2, 3]));
+        copy.extend(vec![4, 1]);
+        assert_eq!(copy.len(), 4);
+        assert!(copy != tree);
+
+        let mut trees = HashSet::new();
+        trees.insert(tree_of(&[2, 1, 3]));
+        assert_eq!(trees.contains(&tree), true);
+
+        copy.clear();
+        assert_eq!(copy.is_empty(), true);
+        assert_eq!(copy, BST::default());
+
+        let mut map: BSTMap<&str, i32> = vec![("b", 2), ("a", 1)].into_iter().collect();
+        assert_eq!(format!("{:?}", map), r#"{"a": 1, "b": 2}"#);
+        assert_eq!(map.clone(), map);
+        map.extend(vec![("a", 3)]);
+        assert_eq!(map.get("a"), Some(&3));
+        assert_eq!(map.len(), 2);
+        map.clear();
+        assert_eq!(map.is_empty(), true);
+    }
+
+    #[test]
+    fn test_map() {
+        let mut map = BSTMap::new();
+
+        // Check empty map behaves right
+        assert_eq!(map.get("a"), None);
+
+        assert_eq!(map.insert("b".to_string(), 2), None);
+        assert_eq!(map.insert("a".to_string(), 1), None);
+        assert_eq!(map.insert("c".to_string(), 3), None);
+        assert_eq!(map.insert("a".to_string(), 10), Some(1));
+
+        // Look up with borrowed keys
+        assert_eq!(map.get("a"), Some(&10));
+        assert_eq!(map.contains_key("d"), false);
+        if let Some(value) = map.get_mut("b") {
+            *value += 20;
+        }
+        assert_eq!(map.get("b"), Some(&22));
+
+        assert_eq!(map.remove("c"), Some(3));
+        assert_eq!(map.remove("c"), None);
+
+        map.retain(|_, value| *value > 15);
+        assert_eq!(map.iter().map(|(k, v)| (k.as_str(), *v)).collect::<Vec<_>>(), vec![("b", 22)]);
+    }
+}
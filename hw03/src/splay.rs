@@ -0,0 +1,447 @@
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+
+/// A self-adjusting BST. Every access moves the accessed element to the
+/// root, so elements used recently are found quickly again.
+///
+/// Single operations can take linear time, e.g. searching the smallest
+/// element after inserting in ascending order, but any sequence of m
+/// operations takes O(m log n) time, so each costs O(log n) amortized.
+/// When a few elements get most of the accesses, they stay near the root
+/// and are much cheaper than that.
+pub struct SplayTree<T> {
+    root: Link<T>,
+    len: usize,
+}
+
+#[derive(Debug)]
+struct Node<T> {
+    elem: T,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+type Link<T> = Option<Box<Node<T>>>;
+
+impl<T: Ord> Default for SplayTree<T> {
+    fn default() -> Self {
+        SplayTree::new()
+    }
+}
+
+impl<T> Drop for SplayTree<T> {
+    fn drop(&mut self) {
+        drop_link(self.root.take());
+    }
+}
+
+// Splaying readily makes long paths, which would overflow the stack if
+// dropped recursively.
+fn drop_link<T>(link: Link<T>) {
+    let mut stack: Vec<Box<Node<T>>> = link.into_iter().collect();
+    while let Some(mut node) = stack.pop() {
+        stack.extend(node.left.take());
+        stack.extend(node.right.take());
+    }
+}
+
+impl<T: Ord> SplayTree<T> {
+    pub fn new() -> SplayTree<T> {
+        SplayTree { root: None, len: 0 }
+    }
+
+    /// Insert an element into the tree, leaving it at the root. Return true
+    /// if successful, or false if the element was already in the tree.
+    pub fn insert(&mut self, elem: T) -> bool {
+        let mut root = match self.root.take() {
+            None => {
+                self.root = Some(Box::new(Node { elem, left: None, right: None }));
+                self.len += 1;
+                return true;
+            },
+            Some(root) => splay(root, &elem)
+        };
+        // After splaying, the root holds the element closest to `elem`, so
+        // the new node can split the tree between its subtrees.
+        let node = match elem.cmp(&root.elem) {
+            Ordering::Less => Node { elem, left: root.left.take(), right: Some(root) },
+            Ordering::Greater => Node { elem, right: root.right.take(), left: Some(root) },
+            Ordering::Equal => {
+                self.root = Some(root);
+                return false;
+            }
+        };
+        self.root = Some(Box::new(node));
+        self.len += 1;
+        true
+    }
+
+    /// Search for an element in the tree, moving it to the root if found
+    /// (or else an element next to it). Return true
+    /// if the element was found.
+    pub fn search<Q: Ord + ?Sized>(&mut self, elem: &Q) -> bool where T: Borrow<Q> {
+        self.root = self.root.take().map(|root| splay(root, elem));
+        self.root.as_ref().is_some_and(|root| root.elem.borrow() == elem)
+    }
+
+    /// Remove an element from the tree. Return true
+    /// if the element was in the tree.
+    pub fn remove<Q: Ord + ?Sized>(&mut self, elem: &Q) -> bool where T: Borrow<Q> {
+        self.take(elem).is_some()
+    }
+
+    /// Remove an element from the tree and return it, or None if the
+    /// element was not in the tree.
+    pub fn take<Q: Ord + ?Sized>(&mut self, elem: &Q) -> Option<T> where T: Borrow<Q> {
+        if !self.search(elem) {
+            return None;
+        }
+        let mut root = self.root.take()?;
+        // Splaying the left subtree for `elem` brings its largest element
+        // to the top, which then has no right child to make room for the
+        // right subtree.
+        self.root = match root.left.take() {
+            None => root.right.take(),
+            Some(left) => {
+                let mut left = splay(left, elem);
+                left.right = root.right.take();
+                Some(left)
+            }
+        };
+        self.len -= 1;
+        Some(root.elem)
+    }
+}
+
+impl<T> SplayTree<T> {
+    /// Return the number of elements in the tree.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return true if the tree has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Remove all the elements.
+    pub fn clear(&mut self) {
+        drop_link(self.root.take());
+        self.len = 0;
+    }
+}
+
+// Top-down splaying: walk down from the root toward `elem`, moving the
+// nodes passed by into a left tree of smaller elements and a right tree of
+// larger ones. Where the path goes the same way twice, the nodes are
+// rotated first, which roughly halves the depth of the path. The last node
+// reached becomes the root, with the left and right trees as its subtrees.
+//
+// Rather than keeping pointers to the ends of the left and right trees,
+// the nodes for each are collected in a stack and linked at the end.
+fn splay<T: Borrow<Q>, Q: Ord + ?Sized>(mut root: Box<Node<T>>, elem: &Q) -> Box<Node<T>> {
+    // `smaller` only grows larger elements, each to become the right
+    // child of the one before; `larger` the other way around.
+    let mut smaller: Vec<Box<Node<T>>> = vec![];
+    let mut larger: Vec<Box<Node<T>>> = vec![];
+    loop {
+        match elem.cmp(root.elem.borrow()) {
+            Ordering::Less => {
+                let mut left = match root.left.take() {
+                    None => break,
+                    Some(left) => left
+                };
+                if elem < left.elem.borrow() {
+                    // Rotate right
+                    root.left = left.right.take();
+                    left.right = Some(root);
+                    root = left;
+                    left = match root.left.take() {
+                        None => break,
+                        Some(left) => left
+                    };
+                }
+                larger.push(root);
+                root = left;
+            },
+            Ordering::Greater => {
+                let mut right = match root.right.take() {
+                    None => break,
+                    Some(right) => right
+                };
+                if elem > right.elem.borrow() {
+                    // Rotate left
+                    root.right = right.left.take();
+                    right.left = Some(root);
+                    root = right;
+                    right = match root.right.take() {
+                        None => break,
+                        Some(right) => right
+                    };
+                }
+                smaller.push(root);
+                root = right;
+            },
+            Ordering::Equal => break
+        }
+    }
+    let mut left = root.left.take();
+    while let Some(mut node) = smaller.pop() {
+        node.right = left;
+        left = Some(node);
+    }
+    let mut right = root.right.take();
+    while let Some(mut node) = larger.pop() {
+        node.left = right;
+        right = Some(node);
+    }
+    root.left = left;
+    root.right = right;
+    root
+}
+
+// ============================================== IntoIter
+
+impl<T> IntoIterator for SplayTree<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(mut self) -> Self::IntoIter {
+        let mut iter = IntoIter {
+            stack: vec![]
+        };
+        iter.push_left_edge(self.root.take());
+        iter
+    }
+}
+
+pub struct IntoIter<T> {
+    stack: Vec<Box<Node<T>>>
+}
+
+impl<T> IntoIter<T> {
+    fn push_left_edge(&mut self, mut link: Link<T>) {
+        while let Some(mut node) = link {
+            link = node.left.take();
+            self.stack.push(node);
+        }
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.stack.pop().map(|mut node| {
+            self.push_left_edge(node.right.take());
+            node.elem
+        })
+    }
+}
+
+impl<T> Drop for IntoIter<T> {
+    fn drop(&mut self) {
+        for _ in self {}
+    }
+}
+
+// ============================================== Iter
+
+pub struct Iter<'a, T: 'a> {
+    stack: Vec<&'a Node<T>>
+}
+
+impl<'a, T: 'a> Iter<'a, T> {
+    fn push_left_edge(&mut self, mut link: Option<&'a Node<T>>) {
+        while let Some(node) = link {
+            self.stack.push(node);
+            link = node.left.as_deref();
+        }
+    }
+}
+
+impl<T> SplayTree<T> {
+    /// Visit the elements in ascending order. Unlike `search`, this does
+    /// not change the shape of the tree.
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut iter = Iter {
+            stack: vec![]
+        };
+        iter.push_left_edge(self.root.as_deref());
+        iter
+    }
+}
+
+impl<'a, T: 'a> IntoIterator for &'a SplayTree<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T: 'a> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.stack.pop().map(|node| {
+            self.push_left_edge(node.right.as_deref());
+            &node.elem
+        })
+    }
+}
+
+// ============================================== Traits
+
+impl<T: Ord + Clone> Clone for SplayTree<T> {
+    /// Copy the elements into a new tree. Inserting them in order only
+    /// takes constant time each, but leaves a single path, like many
+    /// splay trees have anyway.
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SplayTree<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for SplayTree<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for SplayTree<T> {}
+
+impl<T: Hash> Hash for SplayTree<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for elem in self {
+            elem.hash(state);
+        }
+        state.write_usize(self.len);
+    }
+}
+
+impl<T: Ord> FromIterator<T> for SplayTree<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tree = SplayTree::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<T: Ord> Extend<T> for SplayTree<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.insert(elem);
+        }
+    }
+}
+
+// ============================================== Test
+
+#[cfg(test)]
+mod test {
+    use super::{Link, SplayTree};
+
+    fn tree_of(elems: &[i32]) -> SplayTree<i32> {
+        let mut tree = SplayTree::new();
+        for elem in elems {
+            tree.insert(*elem);
+        }
+        tree
+    }
+
+    fn root(tree: &SplayTree<i32>) -> Option<i32> {
+        tree.root.as_ref().map(|node| node.elem)
+    }
+
+    fn depth(link: &Link<i32>) -> usize {
+        link.as_ref().map_or(0, |node| 1 + depth(&node.left).max(depth(&node.right)))
+    }
+
+    #[test]
+    fn basics() {
+        let mut tree = SplayTree::new();
+
+        // Check empty tree behaves right
+        assert_eq!(tree.search(&1), false);
+        assert_eq!(tree.remove(&1), false);
+
+        // Populate tree
+        assert_eq!(tree.insert(2), true);
+        assert_eq!(tree.insert(1), true);
+        assert_eq!(tree.insert(3), true);
+        assert_eq!(tree.insert(1), false);
+        assert_eq!(tree.len(), 3);
+
+        // Found elements move to the root
+        assert_eq!(tree.search(&2), true);
+        assert_eq!(root(&tree), Some(2));
+        assert_eq!(tree.search(&4), false);
+        assert_eq!(root(&tree), Some(3));
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn remove() {
+        let mut tree = tree_of(&[4, 2, 6, 1, 3, 5, 7]);
+
+        assert_eq!(tree.remove(&4), true);
+        assert_eq!(tree.remove(&4), false);
+        assert_eq!(tree.take(&1), Some(1));
+        assert_eq!(tree.take(&7), Some(7));
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), vec![2, 3, 5, 6]);
+        assert_eq!(tree.len(), 4);
+
+        for elem in &[2, 3, 5, 6] {
+            assert_eq!(tree.remove(elem), true);
+        }
+        assert_eq!(tree.is_empty(), true);
+        assert_eq!(tree.root.is_none(), true);
+    }
+
+    #[test]
+    fn splaying_shortens_paths() {
+        // Inserting in order leaves a single path
+        let mut tree = tree_of(&(0..1000).collect::<Vec<_>>());
+        assert_eq!(depth(&tree.root), 1000);
+
+        // Reaching the deepest element about halves it
+        assert_eq!(tree.search(&0), true);
+        assert_eq!(root(&tree), Some(0));
+        assert!(depth(&tree.root) < 510);
+
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn long_paths_drop() {
+        let tree = tree_of(&(0..200_000).collect::<Vec<_>>());
+        assert_eq!(tree.into_iter().take(3).collect::<Vec<_>>(), vec![0, 1, 2]);
+        drop(tree_of(&(0..200_000).collect::<Vec<_>>()));
+    }
+
+    #[test]
+    fn traits() {
+        let mut tree: SplayTree<i32> = vec![3, 1, 2].into_iter().collect();
+        assert_eq!(format!("{:?}", tree), "{1, 2, 3}");
+
+        // Searching changes the shape but not the elements
+        let copy = tree.clone();
+        tree.search(&1);
+        assert_eq!(copy, tree);
+
+        tree.extend(vec![4]);
+        assert!(copy != tree);
+        tree.clear();
+        assert_eq!(tree.is_empty(), true);
+        assert_eq!(tree.search(&1), false);
+    }
+}
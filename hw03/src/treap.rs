@@ -0,0 +1,702 @@
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::{Bound, RangeBounds};
+
+/// A randomized balanced BST. Every node gets a random priority, and the
+/// tree is kept a heap of priorities besides a BST of elements, which makes
+/// its shape that of a BST built by inserting in random order: its depth is
+/// O(log n) with high probability, whatever the order of operations.
+///
+/// All the updates are made of two primitives: `split`, which cuts a
+/// treap in two by an element, and `merge`, which joins two treaps whose
+/// elements do not overlap. Both take O(log n) expected time.
+#[derive(Clone)]
+pub struct Treap<T> {
+    root: Link<T>,
+    priorities: Priorities,
+}
+
+/// A sequence kept in a treap with implicit keys: the position of an
+/// element is given by the sizes of the subtrees before it rather than by
+/// comparing elements. Inserting, removing, splitting and joining at any
+/// position take O(log n) expected time.
+#[derive(Clone)]
+pub struct ImplicitTreap<T> {
+    root: Link<T>,
+    priorities: Priorities,
+}
+
+#[derive(Clone, Debug)]
+struct Node<T> {
+    elem: T,
+    priority: u64,
+    // The number of elements in the subtree, which implicit keys need.
+    size: usize,
+    left: Link<T>,
+    right: Link<T>,
+}
+
+type Link<T> = Option<Box<Node<T>>>;
+
+// A small random number generator (SplitMix64), so a treap built with the
+// same seed and operations always gets the same shape.
+#[derive(Clone, Debug)]
+struct Priorities(u64);
+
+impl Priorities {
+    // Seed from the randomly keyed hasher of the standard library.
+    fn random() -> Priorities {
+        Priorities(RandomState::new().build_hasher().finish())
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn node<T>(&mut self, elem: T) -> Link<T> {
+        Some(Box::new(Node {
+            elem,
+            priority: self.next(),
+            size: 1,
+            left: None,
+            right: None
+        }))
+    }
+}
+
+fn size<T>(link: &Link<T>) -> usize {
+    link.as_ref().map_or(0, |node| node.size)
+}
+
+fn update<T>(mut node: Box<Node<T>>) -> Link<T> {
+    node.size = 1 + size(&node.left) + size(&node.right);
+    Some(node)
+}
+
+// Join two subtrees, where all the elements of `left` come before those of
+// `right`. The root with the higher priority stays on top.
+fn merge<T>(left: Link<T>, right: Link<T>) -> Link<T> {
+    match (left, right) {
+        (None, link) | (link, None) => link,
+        (Some(mut left), Some(mut right)) => {
+            if left.priority > right.priority {
+                left.right = merge(left.right.take(), Some(right));
+                update(left)
+            } else {
+                right.left = merge(Some(left), right.left.take());
+                update(right)
+            }
+        }
+    }
+}
+
+// Split a subtree into the elements for which `goes_left` is true, and the
+// rest. `goes_left` must be true for a prefix of the elements in order.
+fn split<T, F: Fn(&T) -> bool>(link: Link<T>, goes_left: &F) -> (Link<T>, Link<T>) {
+    match link {
+        None => (None, None),
+        Some(mut node) => {
+            if goes_left(&node.elem) {
+                let (less, rest) = split(node.right.take(), goes_left);
+                node.right = less;
+                (update(node), rest)
+            } else {
+                let (less, rest) = split(node.left.take(), goes_left);
+                node.left = rest;
+                (less, update(node))
+            }
+        }
+    }
+}
+
+// Split a subtree into its first `index` elements and the rest.
+fn split_at<T>(link: Link<T>, index: usize) -> (Link<T>, Link<T>) {
+    match link {
+        None => (None, None),
+        Some(mut node) => {
+            let left_size = size(&node.left);
+            if index <= left_size {
+                let (less, rest) = split_at(node.left.take(), index);
+                node.left = rest;
+                (less, update(node))
+            } else {
+                let (less, rest) = split_at(node.right.take(), index - left_size - 1);
+                node.right = less;
+                (update(node), rest)
+            }
+        }
+    }
+}
+
+// ============================================== Treap
+
+impl<T: Ord> Default for Treap<T> {
+    fn default() -> Self {
+        Treap::new()
+    }
+}
+
+impl<T: Ord> Treap<T> {
+    /// Create an empty treap with randomly seeded priorities.
+    pub fn new() -> Treap<T> {
+        Treap { root: None, priorities: Priorities::random() }
+    }
+
+    /// Create an empty treap whose priorities come from `seed`, so the
+    /// same operations always build the same shape.
+    pub fn with_seed(seed: u64) -> Treap<T> {
+        Treap { root: None, priorities: Priorities(seed) }
+    }
+
+    /// Insert an element into the treap. Return true
+    /// if successful, or false if the element was already in the treap.
+    pub fn insert(&mut self, elem: T) -> bool {
+        if self.search(&elem) {
+            return false;
+        }
+        let (less, rest) = split(self.root.take(), &|other: &T| *other < elem);
+        let node = self.priorities.node(elem);
+        self.root = merge(merge(less, node), rest);
+        true
+    }
+
+    /// Search for an element in the treap. Return true
+    /// if the element was found.
+    pub fn search<Q: Ord + ?Sized>(&self, elem: &Q) -> bool where T: Borrow<Q> {
+        let mut cur = self.root.as_ref();
+        while let Some(node) = cur {
+            match elem.cmp(node.elem.borrow()) {
+                Ordering::Less => cur = node.left.as_ref(),
+                Ordering::Greater => cur = node.right.as_ref(),
+                Ordering::Equal => return true
+            }
+        }
+        false
+    }
+
+    /// Remove an element from the treap. Return true
+    /// if the element was in the treap.
+    pub fn remove<Q: Ord + ?Sized>(&mut self, elem: &Q) -> bool where T: Borrow<Q> {
+        self.take(elem).is_some()
+    }
+
+    /// Remove an element from the treap and return it, or None if the
+    /// element was not in the treap.
+    pub fn take<Q: Ord + ?Sized>(&mut self, elem: &Q) -> Option<T> where T: Borrow<Q> {
+        let (less, rest) = split(self.root.take(), &|other: &T| other.borrow() < elem);
+        let (found, greater) = split(rest, &|other: &T| other.borrow() <= elem);
+        self.root = merge(less, greater);
+        found.map(|node| node.elem)
+    }
+
+    /// Remove all the elements within `range`, and return how many there
+    /// were. Takes O(log n) expected time besides dropping them.
+    pub fn remove_range<Q: Ord + ?Sized, R: RangeBounds<Q>>(&mut self, range: R) -> usize where T: Borrow<Q> {
+        let (less, rest) = split(self.root.take(), &|elem: &T| match range.start_bound() {
+            Bound::Included(start) => elem.borrow() < start,
+            Bound::Excluded(start) => elem.borrow() <= start,
+            Bound::Unbounded => false
+        });
+        let (within, greater) = split(rest, &|elem: &T| match range.end_bound() {
+            Bound::Included(end) => elem.borrow() <= end,
+            Bound::Excluded(end) => elem.borrow() < end,
+            Bound::Unbounded => true
+        });
+        self.root = merge(less, greater);
+        size(&within)
+    }
+
+    /// Split the treap at `elem`. Afterwards `self` contains the elements
+    /// less than `elem`, and the returned treap contains the rest.
+    pub fn split_off<Q: Ord + ?Sized>(&mut self, elem: &Q) -> Treap<T> where T: Borrow<Q> {
+        let (less, rest) = split(self.root.take(), &|other: &T| other.borrow() < elem);
+        self.root = less;
+        let seed = self.priorities.next();
+        Treap { root: rest, priorities: Priorities(seed) }
+    }
+
+    /// Move all the elements of `other` into `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if some element of `other` is not greater than all the
+    /// elements of `self`.
+    pub fn join(&mut self, mut other: Treap<T>) {
+        if let (Some(max), Some(min)) = (self.max(), other.min()) {
+            assert!(max < min, "join requires every element of `other` to be greater");
+        }
+        self.root = merge(self.root.take(), other.root.take());
+    }
+
+    /// Return the smallest element in the treap, or None if it is empty.
+    pub fn min(&self) -> Option<&T> {
+        let mut node = self.root.as_ref()?;
+        while let Some(ref left) = node.left {
+            node = left;
+        }
+        Some(&node.elem)
+    }
+
+    /// Return the largest element in the treap, or None if it is empty.
+    pub fn max(&self) -> Option<&T> {
+        let mut node = self.root.as_ref()?;
+        while let Some(ref right) = node.right {
+            node = right;
+        }
+        Some(&node.elem)
+    }
+}
+
+impl<T> Treap<T> {
+    /// Return the number of elements in the treap.
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    /// Return true if the treap has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Remove all the elements.
+    pub fn clear(&mut self) {
+        self.root = None;
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter::new(&self.root)
+    }
+}
+
+impl<'a, T: 'a> IntoIterator for &'a Treap<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// ============================================== ImplicitTreap
+
+impl<T> Default for ImplicitTreap<T> {
+    fn default() -> Self {
+        ImplicitTreap::new()
+    }
+}
+
+impl<T> ImplicitTreap<T> {
+    /// Create an empty sequence with randomly seeded priorities.
+    pub fn new() -> ImplicitTreap<T> {
+        ImplicitTreap { root: None, priorities: Priorities::random() }
+    }
+
+    /// Create an empty sequence whose priorities come from `seed`.
+    pub fn with_seed(seed: u64) -> ImplicitTreap<T> {
+        ImplicitTreap { root: None, priorities: Priorities(seed) }
+    }
+
+    /// Return the number of elements in the sequence.
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    /// Return true if the sequence has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Remove all the elements.
+    pub fn clear(&mut self) {
+        self.root = None;
+    }
+
+    /// Append an element to the back of the sequence.
+    pub fn push(&mut self, elem: T) {
+        let node = self.priorities.node(elem);
+        self.root = merge(self.root.take(), node);
+    }
+
+    /// Insert an element at position `index`, shifting the elements after
+    /// it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    pub fn insert(&mut self, index: usize, elem: T) {
+        assert!(index <= self.len(), "insertion index out of bounds");
+        let (before, after) = split_at(self.root.take(), index);
+        let node = self.priorities.node(elem);
+        self.root = merge(merge(before, node), after);
+    }
+
+    /// Remove and return the element at position `index`, or None if the
+    /// sequence is not that long.
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        if index >= self.len() {
+            return None;
+        }
+        let (before, rest) = split_at(self.root.take(), index);
+        let (found, after) = split_at(rest, 1);
+        self.root = merge(before, after);
+        found.map(|node| node.elem)
+    }
+
+    /// Remove the elements at the positions within `range`, and return how
+    /// many there were.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range starts after it ends or ends after the sequence.
+    pub fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) -> usize {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.checked_add(1).expect("range out of bounds"),
+            Bound::Unbounded => 0
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.checked_add(1).expect("range out of bounds"),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.len()
+        };
+        assert!(start <= end && end <= self.len(), "range out of bounds");
+        let (before, rest) = split_at(self.root.take(), start);
+        let (_, after) = split_at(rest, end - start);
+        self.root = merge(before, after);
+        end - start
+    }
+
+    /// Return the element at position `index`.
+    pub fn get(&self, mut index: usize) -> Option<&T> {
+        let mut cur = self.root.as_ref();
+        while let Some(node) = cur {
+            let left_size = size(&node.left);
+            match index.cmp(&left_size) {
+                Ordering::Less => cur = node.left.as_ref(),
+                Ordering::Equal => return Some(&node.elem),
+                Ordering::Greater => {
+                    index -= left_size + 1;
+                    cur = node.right.as_ref();
+                }
+            }
+        }
+        None
+    }
+
+    /// Split the sequence at `index`. Afterwards `self` contains the first
+    /// `index` elements, and the returned sequence contains the rest.
+    pub fn split_off(&mut self, index: usize) -> ImplicitTreap<T> {
+        let (before, after) = split_at(self.root.take(), index);
+        self.root = before;
+        let seed = self.priorities.next();
+        ImplicitTreap { root: after, priorities: Priorities(seed) }
+    }
+
+    /// Move all the elements of `other` to the back of `self`.
+    pub fn append(&mut self, other: &mut ImplicitTreap<T>) {
+        self.root = merge(self.root.take(), other.root.take());
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter::new(&self.root)
+    }
+}
+
+impl<'a, T: 'a> IntoIterator for &'a ImplicitTreap<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+// ============================================== Iter
+
+pub struct Iter<'a, T: 'a> {
+    stack: Vec<&'a Node<T>>
+}
+
+impl<'a, T: 'a> Iter<'a, T> {
+    fn new(root: &'a Link<T>) -> Iter<'a, T> {
+        let mut iter = Iter {
+            stack: vec![]
+        };
+        iter.push_left_edge(root.as_deref());
+        iter
+    }
+
+    fn push_left_edge(&mut self, mut link: Option<&'a Node<T>>) {
+        while let Some(node) = link {
+            self.stack.push(node);
+            link = node.left.as_deref();
+        }
+    }
+}
+
+impl<'a, T: 'a> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.stack.pop().map(|node| {
+            self.push_left_edge(node.right.as_deref());
+            &node.elem
+        })
+    }
+}
+
+// ============================================== Traits
+
+impl<T: fmt::Debug> fmt::Debug for Treap<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ImplicitTreap<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+// Two treaps are equal when they hold the same elements, whatever their
+// priorities.
+
+impl<T: PartialEq> PartialEq for Treap<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for Treap<T> {}
+
+impl<T: PartialEq> PartialEq for ImplicitTreap<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for ImplicitTreap<T> {}
+
+impl<T: Hash> Hash for Treap<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for elem in self {
+            elem.hash(state);
+        }
+        state.write_usize(self.len());
+    }
+}
+
+impl<T: Hash> Hash for ImplicitTreap<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for elem in self {
+            elem.hash(state);
+        }
+        state.write_usize(self.len());
+    }
+}
+
+impl<T: Ord> FromIterator<T> for Treap<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut treap = Treap::new();
+        treap.extend(iter);
+        treap
+    }
+}
+
+impl<T: Ord> Extend<T> for Treap<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.insert(elem);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for ImplicitTreap<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut seq = ImplicitTreap::new();
+        seq.extend(iter);
+        seq
+    }
+}
+
+impl<T> Extend<T> for ImplicitTreap<T> {
+    /// Push the elements to the back of the sequence.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.push(elem);
+        }
+    }
+}
+
+// ============================================== Test
+
+#[cfg(test)]
+mod test {
+    use super::{ImplicitTreap, Link, Treap};
+
+    // Check the heap order of priorities and the sizes, returning the size.
+    fn check(link: &Link<i32>) -> usize {
+        link.as_ref().map_or(0, |node| {
+            for child in [&node.left, &node.right].iter() {
+                if let Some(ref child) = **child {
+                    assert!(child.priority <= node.priority);
+                }
+            }
+            let size = 1 + check(&node.left) + check(&node.right);
+            assert_eq!(node.size, size);
+            size
+        })
+    }
+
+    fn depth(link: &Link<i32>) -> usize {
+        link.as_ref().map_or(0, |node| 1 + depth(&node.left).max(depth(&node.right)))
+    }
+
+    fn treap_of(elems: &[i32]) -> Treap<i32> {
+        let mut treap = Treap::with_seed(7);
+        for elem in elems {
+            treap.insert(*elem);
+        }
+        treap
+    }
+
+    #[test]
+    fn basics() {
+        let mut treap = Treap::new();
+
+        // Check empty treap behaves right
+        assert_eq!(treap.search(&1), false);
+        assert_eq!(treap.remove(&1), false);
+
+        // Populate treap
+        assert_eq!(treap.insert(2), true);
+        assert_eq!(treap.insert(1), true);
+        assert_eq!(treap.insert(3), true);
+        assert_eq!(treap.insert(1), false);
+        assert_eq!(treap.len(), 3);
+        check(&treap.root);
+
+        assert_eq!(treap.search(&1), true);
+        assert_eq!(treap.search(&4), false);
+        assert_eq!(treap.take(&2), Some(2));
+        assert_eq!(treap.remove(&2), false);
+        assert_eq!(treap.iter().cloned().collect::<Vec<_>>(), vec![1, 3]);
+        check(&treap.root);
+    }
+
+    #[test]
+    fn balanced_and_seeded() {
+        // Inserting in order still gives a shallow tree
+        let treap = treap_of(&(0..10_000).collect::<Vec<_>>());
+        assert_eq!(check(&treap.root), 10_000);
+        assert!(depth(&treap.root) < 60);
+
+        // The same seed gives the same shape
+        let again = treap_of(&(0..10_000).collect::<Vec<_>>());
+        assert_eq!(depth(&treap.root), depth(&again.root));
+        assert_eq!(treap.root.as_ref().map(|node| node.elem), again.root.as_ref().map(|node| node.elem));
+    }
+
+    #[test]
+    fn remove_range() {
+        let mut treap = treap_of(&(0..20).collect::<Vec<_>>());
+
+        assert_eq!(treap.remove_range(5..10), 5);
+        assert_eq!(treap.remove_range(15..), 5);
+        assert_eq!(treap.remove_range(..=1), 2);
+        assert_eq!(treap.remove_range(5..10), 0);
+        assert_eq!(treap.iter().cloned().collect::<Vec<_>>(), vec![2, 3, 4, 10, 11, 12, 13, 14]);
+        check(&treap.root);
+    }
+
+    #[test]
+    fn split_off_and_join() {
+        let mut treap = treap_of(&[1, 2, 3, 4, 5]);
+        let rest = treap.split_off(&3);
+        assert_eq!(treap.iter().cloned().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(rest.iter().cloned().collect::<Vec<_>>(), vec![3, 4, 5]);
+
+        treap.join(rest);
+        assert_eq!(treap.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        check(&treap.root);
+    }
+
+    #[test]
+    #[should_panic]
+    fn join_overlapping() {
+        let mut treap = treap_of(&[1, 5]);
+        treap.join(treap_of(&[3, 7]));
+    }
+
+    #[test]
+    fn implicit() {
+        let mut seq = ImplicitTreap::with_seed(3);
+        for elem in 0..10 {
+            seq.push(elem);
+        }
+        seq.insert(0, -1);
+        seq.insert(5, 100);
+        seq.insert(12, 200);
+        assert_eq!(seq.iter().cloned().collect::<Vec<_>>(), vec![-1, 0, 1, 2, 3, 100, 4, 5, 6, 7, 8, 9, 200]);
+        assert_eq!(seq.get(5), Some(&100));
+        assert_eq!(seq.get(13), None);
+
+        assert_eq!(seq.remove(5), Some(100));
+        assert_eq!(seq.remove(12), None);
+        assert_eq!(seq.remove_range(1..4), 3);
+        assert_eq!(seq.iter().cloned().collect::<Vec<_>>(), vec![-1, 3, 4, 5, 6, 7, 8, 9, 200]);
+        check(&seq.root);
+
+        // Move the front to the back
+        let mut back = seq.split_off(3);
+        back.append(&mut seq);
+        assert_eq!(back.iter().cloned().collect::<Vec<_>>(), vec![5, 6, 7, 8, 9, 200, -1, 3, 4]);
+        assert_eq!(seq.is_empty(), true);
+        assert_eq!(back.len(), 9);
+        check(&back.root);
+    }
+
+    #[test]
+    #[should_panic]
+    fn implicit_insert_out_of_bounds() {
+        let mut seq = ImplicitTreap::new();
+        seq.insert(1, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "range out of bounds")]
+    fn implicit_remove_range_to_max() {
+        let mut seq: ImplicitTreap<i32> = (0..3).collect();
+        seq.remove_range(0..=usize::MAX);
+    }
+
+    #[test]
+    fn traits() {
+        let mut treap: Treap<i32> = vec![3, 1, 2].into_iter().collect();
+        assert_eq!(format!("{:?}", treap), "{1, 2, 3}");
+
+        // Treaps with other priorities are still equal
+        assert_eq!(treap_of(&[1, 2, 3]), treap);
+        let copy = treap.clone();
+        treap.extend(vec![4]);
+        assert!(copy != treap);
+        treap.clear();
+        assert_eq!(treap.is_empty(), true);
+
+        let mut seq: ImplicitTreap<i32> = vec![3, 1, 2].into_iter().collect();
+        assert_eq!(format!("{:?}", seq), "[3, 1, 2]");
+        assert_eq!(seq.clone(), seq);
+        seq.extend(vec![1]);
+        assert_eq!(seq.iter().cloned().collect::<Vec<_>>(), vec![3, 1, 2, 1]);
+        seq.clear();
+        assert_eq!(seq.len(), 0);
+    }
+}
@@ -0,0 +1,485 @@
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+/// A singly-linked queue. Besides the head, the list keeps a pointer to its
+/// last node, so elements are pushed at the back and popped at the front in
+/// O(1) time.
+///
+/// The last node is reachable from both the head and the tail pointer, so
+/// all the links are raw pointers, made with `Box::into_raw` and freed with
+/// `Box::from_raw`. With `Box` links and a raw tail pointer, every access
+/// through the boxes, such as pushing or popping at the head, would reborrow
+/// the last node uniquely and, under Stacked Borrows, invalidate the tail
+/// pointer even though the node never moves. The tests are meant to also
+/// pass under `cargo +nightly miri test -p lists`.
+pub struct List<T> {
+    head: Link<T>,
+    tail: Link<T>,
+    len: usize,
+    // The list owns boxed nodes, which the drop checker has to know.
+    _owns: PhantomData<Box<Node<T>>>,
+}
+
+type Link<T> = Option<NonNull<Node<T>>>;
+
+struct Node<T> {
+    elem: T,
+    next: Link<T>,
+}
+
+// The list owns its elements like a `Vec` does, so it can be sent and shared
+// between threads under the same conditions.
+unsafe impl<T: Send> Send for List<T> {}
+unsafe impl<T: Sync> Sync for List<T> {}
+
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        List::new()
+    }
+}
+
+impl<T> List<T> {
+    pub fn new() -> Self {
+        List { head: None, tail: None, len: 0, _owns: PhantomData }
+    }
+
+    /// Add an element at the back of the queue.
+    pub fn push(&mut self, elem: T) {
+        let node = Box::new(Node { elem, next: None });
+        let node = unsafe { NonNull::new_unchecked(Box::into_raw(node)) };
+        match self.tail {
+            Some(old) => unsafe { (*old.as_ptr()).next = Some(node) },
+            None => self.head = Some(node)
+        }
+        self.tail = Some(node);
+        self.len += 1;
+    }
+
+    /// Remove the element at the front of the queue.
+    pub fn pop(&mut self) -> Option<T> {
+        self.head.map(|node| unsafe {
+            let node = Box::from_raw(node.as_ptr());
+            self.head = node.next;
+            if self.head.is_none() {
+                self.tail = None;
+            }
+            self.len -= 1;
+            node.elem
+        })
+    }
+
+    /// Return the element at the front of the queue, which `pop` would
+    /// remove next.
+    pub fn peek(&self) -> Option<&T> {
+        self.head.map(|node| unsafe { &(*node.as_ptr()).elem })
+    }
+
+    pub fn peek_mut(&mut self) -> Option<&mut T> {
+        self.head.map(|node| unsafe { &mut (*node.as_ptr()).elem })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop().is_some() {}
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { next: self.head, len: self.len, _list: PhantomData }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut { next: self.head, len: self.len, _list: PhantomData }
+    }
+}
+
+impl<T> Drop for List<T> {
+    fn drop(&mut self) {
+        // Free the nodes one at a time, so a long queue does not overflow
+        // the stack.
+        self.clear();
+    }
+}
+
+// ============================================== Iterators
+
+pub struct IntoIter<T>(List<T>);
+
+impl<T> IntoIterator for List<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+pub struct Iter<'a, T: 'a> {
+    next: Link<T>,
+    len: usize,
+    _list: PhantomData<&'a T>,
+}
+
+impl<'a, T> IntoIterator for &'a List<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.map(|node| unsafe {
+            self.len -= 1;
+            self.next = (*node.as_ptr()).next;
+            &(*node.as_ptr()).elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+unsafe impl<'a, T: Sync> Send for Iter<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for Iter<'a, T> {}
+
+pub struct IterMut<'a, T: 'a> {
+    next: Link<T>,
+    len: usize,
+    _list: PhantomData<&'a mut T>,
+}
+
+impl<'a, T> IntoIterator for &'a mut List<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.map(|node| unsafe {
+            self.len -= 1;
+            self.next = (*node.as_ptr()).next;
+            &mut (*node.as_ptr()).elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {}
+
+unsafe impl<'a, T: Send> Send for IterMut<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for IterMut<'a, T> {}
+
+// ============================================== Traits
+
+impl<T: Clone> Clone for List<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for List<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for List<T> {}
+
+impl<T: Hash> Hash for List<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for elem in self {
+            elem.hash(state);
+        }
+        state.write_usize(self.len);
+    }
+}
+
+impl<T> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = List::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T> Extend<T> for List<T> {
+    /// Push the elements to the back of the queue.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.push(elem);
+        }
+    }
+}
+
+// A queue is serialized as the sequence of its elements from the front on.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use std::fmt;
+    use std::marker::PhantomData;
+
+    use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
+    use serde::ser::{Serialize, Serializer};
+
+    use super::List;
+
+    impl<T: Serialize> Serialize for List<T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.iter())
+        }
+    }
+
+    impl<'de, T: Deserialize<'de>> Deserialize<'de> for List<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_seq(ListVisitor(PhantomData))
+        }
+    }
+
+    struct ListVisitor<T>(PhantomData<fn() -> List<T>>);
+
+    impl<'de, T: Deserialize<'de>> Visitor<'de> for ListVisitor<T> {
+        type Value = List<T>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a sequence")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut list = List::new();
+            while let Some(elem) = seq.next_element()? {
+                list.push(elem);
+            }
+            Ok(list)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::List;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn basics() {
+        let mut list = List::new();
+
+        // Check empty list behaves right
+        assert_eq!(list.pop(), None);
+
+        // Populate list
+        list.push(1);
+        list.push(2);
+        list.push(3);
+
+        // Check normal removal
+        assert_eq!(list.pop(), Some(1));
+        assert_eq!(list.pop(), Some(2));
+
+        // Push some more just to make sure nothing's corrupted
+        list.push(4);
+        list.push(5);
+
+        // Check normal removal
+        assert_eq!(list.pop(), Some(3));
+        assert_eq!(list.pop(), Some(4));
+
+        // Check exhaustion
+        assert_eq!(list.pop(), Some(5));
+        assert_eq!(list.pop(), None);
+
+        // Check the exhaustion case fixed the pointer right
+        list.push(6);
+        list.push(7);
+        assert_eq!(list.pop(), Some(6));
+        assert_eq!(list.pop(), Some(7));
+        assert_eq!(list.pop(), None);
+    }
+
+    #[test]
+    fn peek() {
+        let mut list = List::new();
+        assert_eq!(list.peek(), None);
+        assert_eq!(list.peek_mut(), None);
+        list.push(1); list.push(2); list.push(3);
+
+        assert_eq!(list.peek(), Some(&1));
+        if let Some(elem) = list.peek_mut() {
+            *elem *= 10;
+        }
+        assert_eq!(list.pop(), Some(10));
+        assert_eq!(list.peek(), Some(&2));
+    }
+
+    #[test]
+    fn into_iter() {
+        let mut list = List::new();
+        list.push(1); list.push(2); list.push(3);
+
+        let mut iter = list.into_iter();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.next(), Some(2));
+        assert_eq!(iter.next(), Some(3));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn iter() {
+        let mut list = List::new();
+        list.push(1); list.push(2); list.push(3);
+
+        let mut iter = list.iter();
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.len(), 1);
+        assert_eq!(iter.next(), Some(&3));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn iter_mut() {
+        let mut list = List::new();
+        list.push(1); list.push(2); list.push(3);
+
+        for elem in &mut list {
+            *elem *= 10;
+        }
+        let mut iter = list.iter_mut();
+        assert_eq!(iter.next(), Some(&mut 10));
+        assert_eq!(iter.next(), Some(&mut 20));
+        assert_eq!(iter.next(), Some(&mut 30));
+        assert_eq!(iter.next(), None);
+
+        // The tail is still right after the elements were changed
+        list.push(4);
+        assert_eq!(list.iter().cloned().collect::<Vec<_>>(), vec![10, 20, 30, 4]);
+    }
+
+    #[test]
+    fn long_drop() {
+        // Dropping node by node recursively would overflow the stack. Miri
+        // is far too slow for that many nodes, but checks the frees instead.
+        let len = if cfg!(miri) { 1000 } else { 1_000_000 };
+        let mut list = List::new();
+        for i in 0..len {
+            list.push(i);
+        }
+        drop(list);
+    }
+
+    #[test]
+    fn random_against_vecdeque() {
+        // A small xorshift generator, so a failure can be replayed
+        let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for _ in 0..50 {
+            let mut list = List::new();
+            let mut expected = VecDeque::new();
+            for _ in 0..500 {
+                match next() % 5 {
+                    // Push a little more often than pop, so the queue grows
+                    // as well as runs empty.
+                    0 | 1 => {
+                        let elem = next() % 1000;
+                        list.push(elem);
+                        expected.push_back(elem);
+                    },
+                    2 => assert_eq!(list.pop(), expected.pop_front()),
+                    3 => {
+                        if let Some(elem) = list.peek_mut() {
+                            *elem += 1;
+                        }
+                        if let Some(elem) = expected.front_mut() {
+                            *elem += 1;
+                        }
+                    },
+                    _ => assert_eq!(list.peek(), expected.front())
+                }
+                assert_eq!(list.len(), expected.len());
+            }
+            assert!(list.iter().eq(expected.iter()));
+            assert!(list.into_iter().eq(expected.into_iter()));
+        }
+    }
+
+    #[test]
+    fn traits() {
+        let mut list: List<i32> = vec![1, 2, 3].into_iter().collect();
+        assert_eq!(format!("{:?}", list), "[1, 2, 3]");
+
+        let copy = list.clone();
+        assert_eq!(copy, list);
+        list.extend(vec![4]);
+        assert!(copy != list);
+        assert_eq!(list.len(), 4);
+
+        list.clear();
+        assert_eq!(list.is_empty(), true);
+        list.push(5);
+        assert_eq!(list.peek(), Some(&5));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        let list: List<i32> = vec![1, 2, 3].into_iter().collect();
+        let json = serde_json::to_string(&list).unwrap();
+        assert_eq!(json, "[1,2,3]");
+
+        let mut list: List<i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(list.pop(), Some(1));
+        assert_eq!(serde_json::from_str::<List<i32>>("[]").unwrap().peek(), None);
+    }
+}
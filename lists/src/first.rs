@@ -1,4 +1,7 @@
 
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
 use std::mem;
 
 struct Node {
@@ -15,6 +18,12 @@ pub struct List {
     head: Link
 }
 
+impl Default for List {
+    fn default() -> Self {
+        List::new()
+    }
+}
+
 impl List {
     pub fn new() -> List {
         List {
@@ -24,7 +33,7 @@ impl List {
 
     pub fn push(&mut self, elem: i32) {
         let new_node = Node {
-            elem: elem,
+            elem,
             next: mem::replace(&mut self.head, Link::Empty)
         };
 
@@ -41,6 +50,44 @@ impl List {
             }
         }
     }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { next: &self.head }
+    }
+
+    /// Return the number of elements. Takes O(n) time.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self.head {
+            Link::Empty => true,
+            Link::More(_) => false
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = List::new();
+    }
+}
+
+pub struct Iter<'a> {
+    next: &'a Link,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a i32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match *self.next {
+            Link::Empty => None,
+            Link::More(ref node) => {
+                self.next = &node.next;
+                Some(&node.elem)
+            }
+        }
+    }
 }
 
 impl Drop for List {
@@ -56,6 +103,66 @@ impl Drop for List {
     }
 }
 
+// ============================================== Traits
+
+impl Clone for List {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl fmt::Debug for List {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for List {}
+
+impl Hash for List {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut len = 0;
+        for elem in self.iter() {
+            elem.hash(state);
+            len += 1;
+        }
+        state.write_usize(len);
+    }
+}
+
+// A collected list keeps the order of the iterator, with its first
+// element on top.
+impl FromIterator<i32> for List {
+    fn from_iter<I: IntoIterator<Item = i32>>(iter: I) -> Self {
+        let mut list = List::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl Extend<i32> for List {
+    /// Add the elements after the last one of the list, in order.
+    fn extend<I: IntoIterator<Item = i32>>(&mut self, iter: I) {
+        let mut tail = &mut self.head;
+        while let Link::More(ref mut node) = *tail {
+            tail = &mut node.next;
+        }
+        for elem in iter {
+            *tail = Link::More(Box::new(Node { elem, next: Link::Empty }));
+            tail = match *tail {
+                Link::More(ref mut node) => &mut node.next,
+                Link::Empty => unreachable!()
+            };
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::List;
@@ -88,4 +195,21 @@ mod test {
         assert_eq!(list.pop(), Some(1));
         assert_eq!(list.pop(), None);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn traits() {
+        let mut list: List = vec![1, 2, 3].into_iter().collect();
+        assert_eq!(format!("{:?}", list), "[1, 2, 3]");
+        assert_eq!(list.len(), 3);
+
+        let copy = list.clone();
+        assert_eq!(copy, list);
+        list.extend(vec![4, 5]);
+        assert_eq!(list.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        assert!(copy != list);
+
+        list.clear();
+        assert!(list.is_empty());
+        assert_eq!(list.pop(), None);
+    }
+}
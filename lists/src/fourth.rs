@@ -0,0 +1,624 @@
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+/// A doubly-linked deque. Every node knows the nodes on both sides of it,
+/// so elements are added and removed at both ends in O(1) time, and two
+/// deques are joined in O(1) time.
+///
+/// The nodes are owned through raw pointers, since a node has two owners
+/// as far as the borrow checker is concerned. Every pointer comes from
+/// `Box::into_raw` and goes back through `Box::from_raw` exactly once, and
+/// references to the elements are only made while the list is borrowed.
+/// The tests are meant to also pass under `cargo +nightly miri test -p lists`.
+pub struct List<T> {
+    front: Link<T>,
+    back: Link<T>,
+    len: usize,
+    // The list owns boxed nodes, which the drop checker has to know.
+    _owns: PhantomData<Box<Node<T>>>,
+}
+
+type Link<T> = Option<NonNull<Node<T>>>;
+
+struct Node<T> {
+    elem: T,
+    prev: Link<T>,
+    next: Link<T>,
+}
+
+// The list owns its elements like a `Vec` does, so it can be sent and shared
+// between threads under the same conditions.
+unsafe impl<T: Send> Send for List<T> {}
+unsafe impl<T: Sync> Sync for List<T> {}
+
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        List::new()
+    }
+}
+
+impl<T> List<T> {
+    pub fn new() -> Self {
+        List { front: None, back: None, len: 0, _owns: PhantomData }
+    }
+
+    pub fn push_front(&mut self, elem: T) {
+        let node = new_node(elem, None, self.front);
+        match self.front {
+            Some(old) => unsafe { (*old.as_ptr()).prev = Some(node) },
+            None => self.back = Some(node)
+        }
+        self.front = Some(node);
+        self.len += 1;
+    }
+
+    pub fn push_back(&mut self, elem: T) {
+        let node = new_node(elem, self.back, None);
+        match self.back {
+            Some(old) => unsafe { (*old.as_ptr()).next = Some(node) },
+            None => self.front = Some(node)
+        }
+        self.back = Some(node);
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.front.map(|node| unsafe {
+            let node = Box::from_raw(node.as_ptr());
+            self.front = node.next;
+            match self.front {
+                Some(new) => (*new.as_ptr()).prev = None,
+                None => self.back = None
+            }
+            self.len -= 1;
+            node.elem
+        })
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.back.map(|node| unsafe {
+            let node = Box::from_raw(node.as_ptr());
+            self.back = node.prev;
+            match self.back {
+                Some(new) => (*new.as_ptr()).next = None,
+                None => self.front = None
+            }
+            self.len -= 1;
+            node.elem
+        })
+    }
+
+    pub fn peek_front(&self) -> Option<&T> {
+        self.front.map(|node| unsafe { &(*node.as_ptr()).elem })
+    }
+
+    pub fn peek_back(&self) -> Option<&T> {
+        self.back.map(|node| unsafe { &(*node.as_ptr()).elem })
+    }
+
+    pub fn peek_front_mut(&mut self) -> Option<&mut T> {
+        self.front.map(|node| unsafe { &mut (*node.as_ptr()).elem })
+    }
+
+    pub fn peek_back_mut(&mut self) -> Option<&mut T> {
+        self.back.map(|node| unsafe { &mut (*node.as_ptr()).elem })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+
+    /// Move all the elements of `other` to the back of the list, leaving
+    /// `other` empty. Takes O(1) time.
+    pub fn append(&mut self, other: &mut List<T>) {
+        let (front, back) = match (other.front.take(), other.back.take()) {
+            (Some(front), Some(back)) => (front, back),
+            _ => return
+        };
+        match self.back {
+            Some(old) => unsafe {
+                (*old.as_ptr()).next = Some(front);
+                (*front.as_ptr()).prev = Some(old);
+            },
+            None => self.front = Some(front)
+        }
+        self.back = Some(back);
+        self.len += other.len;
+        other.len = 0;
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { front: self.front, back: self.back, len: self.len, _list: PhantomData }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut { front: self.front, back: self.back, len: self.len, _list: PhantomData }
+    }
+}
+
+fn new_node<T>(elem: T, prev: Link<T>, next: Link<T>) -> NonNull<Node<T>> {
+    let node = Box::new(Node { elem, prev, next });
+    unsafe { NonNull::new_unchecked(Box::into_raw(node)) }
+}
+
+impl<T> Drop for List<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+// ============================================== Iterators
+
+pub struct IntoIter<T>(List<T>);
+
+impl<T> IntoIterator for List<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.pop_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+// The iterators over references walk from both ends, and stop when their
+// count of the elements left runs out, which is before the ends cross.
+
+pub struct Iter<'a, T: 'a> {
+    front: Link<T>,
+    back: Link<T>,
+    len: usize,
+    _list: PhantomData<&'a T>,
+}
+
+impl<'a, T> IntoIterator for &'a List<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.front.map(|node| unsafe {
+            self.len -= 1;
+            self.front = (*node.as_ptr()).next;
+            &(*node.as_ptr()).elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.back.map(|node| unsafe {
+            self.len -= 1;
+            self.back = (*node.as_ptr()).prev;
+            &(*node.as_ptr()).elem
+        })
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+unsafe impl<'a, T: Sync> Send for Iter<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for Iter<'a, T> {}
+
+pub struct IterMut<'a, T: 'a> {
+    front: Link<T>,
+    back: Link<T>,
+    len: usize,
+    _list: PhantomData<&'a mut T>,
+}
+
+impl<'a, T> IntoIterator for &'a mut List<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.front.map(|node| unsafe {
+            self.len -= 1;
+            self.front = (*node.as_ptr()).next;
+            &mut (*node.as_ptr()).elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.back.map(|node| unsafe {
+            self.len -= 1;
+            self.back = (*node.as_ptr()).prev;
+            &mut (*node.as_ptr()).elem
+        })
+    }
+}
+
+impl<'a, T> ExactSizeIterator for IterMut<'a, T> {}
+
+unsafe impl<'a, T: Send> Send for IterMut<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for IterMut<'a, T> {}
+
+// ============================================== Traits
+
+impl<T: Clone> Clone for List<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for List<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for List<T> {}
+
+impl<T: Hash> Hash for List<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for elem in self {
+            elem.hash(state);
+        }
+        state.write_usize(self.len);
+    }
+}
+
+impl<T> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = List::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T> Extend<T> for List<T> {
+    /// Push the elements to the back of the list.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.push_back(elem);
+        }
+    }
+}
+
+// A list is serialized as the sequence of its elements from the front on.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use std::fmt;
+    use std::marker::PhantomData;
+
+    use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
+    use serde::ser::{Serialize, Serializer};
+
+    use super::List;
+
+    impl<T: Serialize> Serialize for List<T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.iter())
+        }
+    }
+
+    impl<'de, T: Deserialize<'de>> Deserialize<'de> for List<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_seq(ListVisitor(PhantomData))
+        }
+    }
+
+    struct ListVisitor<T>(PhantomData<fn() -> List<T>>);
+
+    impl<'de, T: Deserialize<'de>> Visitor<'de> for ListVisitor<T> {
+        type Value = List<T>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a sequence")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut list = List::new();
+            while let Some(elem) = seq.next_element()? {
+                list.push_back(elem);
+            }
+            Ok(list)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::List;
+    use std::cell::Cell;
+
+    #[test]
+    fn basics() {
+        let mut list = List::new();
+
+        // Check empty list behaves right
+        assert_eq!(list.pop_front(), None);
+        assert_eq!(list.pop_back(), None);
+        assert_eq!(list.len(), 0);
+
+        // Populate list
+        list.push_front(2);
+        list.push_front(1);
+        list.push_back(3);
+        list.push_back(4);
+        assert_eq!(list.len(), 4);
+
+        // Check normal removal
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_back(), Some(4));
+
+        // Push some more just to make sure nothing's corrupted
+        list.push_back(5);
+        list.push_front(0);
+
+        // Check normal removal
+        assert_eq!(list.pop_back(), Some(5));
+        assert_eq!(list.pop_front(), Some(0));
+
+        // Check exhaustion from both ends
+        assert_eq!(list.pop_back(), Some(3));
+        assert_eq!(list.pop_back(), Some(2));
+        assert_eq!(list.pop_back(), None);
+        assert_eq!(list.pop_front(), None);
+        assert_eq!(list.is_empty(), true);
+
+        // The list still works once emptied
+        list.push_back(6);
+        assert_eq!(list.pop_front(), Some(6));
+        assert_eq!(list.peek_back(), None);
+    }
+
+    #[test]
+    fn peek() {
+        let mut list = List::new();
+        assert_eq!(list.peek_front(), None);
+        assert_eq!(list.peek_back_mut(), None);
+
+        list.push_back(1); list.push_back(2); list.push_back(3);
+        assert_eq!(list.peek_front(), Some(&1));
+        assert_eq!(list.peek_back(), Some(&3));
+
+        *list.peek_front_mut().unwrap() = 10;
+        if let Some(elem) = list.peek_back_mut() {
+            *elem *= 10;
+        }
+        assert_eq!(list.pop_front(), Some(10));
+        assert_eq!(list.pop_back(), Some(30));
+    }
+
+    #[test]
+    fn into_iter() {
+        let list: List<i32> = vec![1, 2, 3, 4].into_iter().collect();
+
+        let mut iter = list.into_iter();
+        assert_eq!(iter.len(), 4);
+        assert_eq!(iter.next(), Some(1));
+        assert_eq!(iter.next_back(), Some(4));
+        assert_eq!(iter.next(), Some(2));
+        assert_eq!(iter.next_back(), Some(3));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+    }
+
+    #[test]
+    fn iter() {
+        let list: List<i32> = vec![1, 2, 3, 4, 5].into_iter().collect();
+
+        let mut iter = list.iter();
+        assert_eq!(iter.next(), Some(&1));
+        assert_eq!(iter.next_back(), Some(&5));
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next_back(), Some(&4));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next(), Some(&3));
+
+        // The ends have met
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        assert_eq!(list.iter().rev().cloned().collect::<Vec<_>>(), vec![5, 4, 3, 2, 1]);
+        assert_eq!(List::<i32>::new().iter().next(), None);
+    }
+
+    #[test]
+    fn iter_mut() {
+        let mut list: List<i32> = vec![1, 2, 3].into_iter().collect();
+
+        // References from both ends are alive at the same time
+        {
+            let mut iter = list.iter_mut();
+            let first = iter.next().unwrap();
+            let last = iter.next_back().unwrap();
+            let middle = iter.next().unwrap();
+            assert_eq!(iter.next_back(), None);
+            *first += 10;
+            *last += 30;
+            *middle += 20;
+        }
+        assert_eq!(list.iter().cloned().collect::<Vec<_>>(), vec![11, 22, 33]);
+
+        for elem in &mut list {
+            *elem = -*elem;
+        }
+        assert_eq!(list.pop_front(), Some(-11));
+    }
+
+    #[test]
+    fn append() {
+        let mut list: List<i32> = vec![1, 2].into_iter().collect();
+        let mut other: List<i32> = vec![3, 4].into_iter().collect();
+
+        list.append(&mut other);
+        assert_eq!(list.len(), 4);
+        assert_eq!(other.is_empty(), true);
+        assert_eq!(other.pop_back(), None);
+        assert_eq!(list.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert_eq!(list.iter().rev().cloned().collect::<Vec<_>>(), vec![4, 3, 2, 1]);
+
+        // Appending an empty list changes nothing
+        list.append(&mut other);
+        assert_eq!(list.len(), 4);
+        assert_eq!(list.peek_back(), Some(&4));
+
+        // Appending onto an empty list moves everything
+        other.append(&mut list);
+        assert_eq!(list.is_empty(), true);
+        assert_eq!(other.peek_front(), Some(&1));
+        assert_eq!(other.peek_back(), Some(&4));
+
+        // Both lists still work afterwards
+        other.push_back(5);
+        list.push_front(0);
+        assert_eq!(other.pop_back(), Some(5));
+        assert_eq!(other.pop_front(), Some(1));
+        assert_eq!(list.pop_back(), Some(0));
+    }
+
+    #[test]
+    fn interleaved_access() {
+        // Mix mutable references with pushes and pops at the other end,
+        // which is where aliasing mistakes would show under Miri.
+        let mut list = List::new();
+        list.push_back(1);
+        for i in 2..10 {
+            *list.peek_front_mut().unwrap() += 1;
+            list.push_back(i);
+            let back = list.peek_back_mut().unwrap();
+            *back *= 2;
+            if i % 3 == 0 {
+                list.pop_front();
+            }
+        }
+        assert_eq!(list.iter().cloned().collect::<Vec<_>>(), vec![8, 10, 12, 14, 16, 18]);
+        assert_eq!(list.iter().rev().cloned().collect::<Vec<_>>(), vec![18, 16, 14, 12, 10, 8]);
+        assert_eq!(list.len(), 6);
+    }
+
+    #[test]
+    fn drops_every_element() {
+        struct Counted<'a>(&'a Cell<usize>);
+
+        impl<'a> Drop for Counted<'a> {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Cell::new(0);
+        {
+            let mut list = List::new();
+            for _ in 0..4 {
+                list.push_back(Counted(&drops));
+                list.push_front(Counted(&drops));
+            }
+            drop(list.pop_back());
+            assert_eq!(drops.get(), 1);
+        }
+        assert_eq!(drops.get(), 8);
+
+        // A partly used iterator drops the rest
+        let list: List<_> = (0..5).map(|_| Counted(&drops)).collect();
+        let mut iter = list.into_iter();
+        iter.next();
+        iter.next_back();
+        drop(iter);
+        assert_eq!(drops.get(), 13);
+    }
+
+    #[test]
+    fn traits() {
+        let mut list: List<i32> = vec![1, 2, 3].into_iter().collect();
+        assert_eq!(format!("{:?}", list), "[1, 2, 3]");
+
+        let copy = list.clone();
+        assert_eq!(copy, list);
+        list.extend(vec![4, 5]);
+        assert!(copy != list);
+        assert_eq!(list.len(), 5);
+
+        list.clear();
+        assert_eq!(list.is_empty(), true);
+        assert_eq!(list.peek_front(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        let list: List<i32> = vec![1, 2, 3].into_iter().collect();
+        let json = serde_json::to_string(&list).unwrap();
+        assert_eq!(json, "[1,2,3]");
+
+        let list: List<i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(list.iter().rev().cloned().collect::<Vec<_>>(), vec![3, 2, 1]);
+        assert_eq!(serde_json::from_str::<List<i32>>("[]").unwrap().peek_back(), None);
+    }
+}
@@ -1,4 +1,14 @@
+// The tests compare results against `true` and `false` with `assert_eq!`.
+#![cfg_attr(test, allow(clippy::bool_assert_comparison))]
+
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 
 pub mod first;
 pub mod second;
-pub mod third;
\ No newline at end of file
+pub mod third;
+pub mod fourth;
+pub mod fifth;
+pub mod sixth;
\ No newline at end of file
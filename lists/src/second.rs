@@ -1,4 +1,9 @@
 
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+
 struct Node<T> {
     elem: T,
     next: Link<T>
@@ -10,6 +15,12 @@ pub struct List<T> {
     head: Link<T>
 }
 
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        List::new()
+    }
+}
+
 impl<T> List<T> {
     pub fn new() -> Self {
         List {
@@ -19,7 +30,7 @@ impl<T> List<T> {
 
     pub fn push(&mut self, elem: T) {
         let new_node = Node {
-            elem: elem,
+            elem,
             next: self.head.take()
         };
 
@@ -45,6 +56,134 @@ impl<T> List<T> {
             &mut node.elem
         })
     }
+
+    /// Return the number of elements. Takes O(n) time.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        *self = List::new();
+    }
+}
+
+// ============================================== Algorithms
+
+// All of these relink the nodes they are given, so no element is moved or
+// reallocated.
+
+impl<T> List<T> {
+    /// Reverse the order of the elements.
+    pub fn reverse(&mut self) {
+        let mut rest = self.head.take();
+        while let Some(mut node) = rest {
+            rest = node.next.take();
+            node.next = self.head.take();
+            self.head = Some(node);
+        }
+    }
+
+    /// Split the list in two at the given index. Return everything from
+    /// `at` on, and keep the first `at` elements.
+    ///
+    /// Panics if `at > len`.
+    pub fn split_off(&mut self, at: usize) -> List<T> {
+        let mut cur = &mut self.head;
+        for _ in 0..at {
+            cur = match *cur {
+                Some(ref mut node) => &mut node.next,
+                None => panic!("split_off index out of bounds")
+            };
+        }
+        List { head: cur.take() }
+    }
+
+    /// Move all the elements of `other` after the last one of the list,
+    /// leaving `other` empty.
+    pub fn append(&mut self, other: &mut List<T>) {
+        let mut tail = &mut self.head;
+        while let Some(ref mut node) = *tail {
+            tail = &mut node.next;
+        }
+        *tail = other.head.take();
+    }
+
+    pub fn contains(&self, elem: &T) -> bool where T: PartialEq {
+        self.iter().any(|other| other == elem)
+    }
+
+    /// Remove the first element for which `pred` is true and return it, or
+    /// None if there is no such element.
+    pub fn remove_first<F: FnMut(&T) -> bool>(&mut self, mut pred: F) -> Option<T> {
+        let mut cur = &mut self.head;
+        loop {
+            match *cur {
+                None => return None,
+                Some(ref node) if pred(&node.elem) => break,
+                Some(ref mut node) => cur = &mut node.next
+            }
+        }
+        cur.take().map(|boxed_node| {
+            let node = *boxed_node;
+            *cur = node.next;
+            node.elem
+        })
+    }
+
+    /// Sort the elements in ascending order. The sort is stable: equal
+    /// elements keep their order. Takes O(n log n) time.
+    pub fn sort(&mut self) where T: Ord {
+        self.sort_by(|a, b| a.cmp(b));
+    }
+
+    /// Sort the elements with a comparator function, keeping the order
+    /// of equal elements.
+    pub fn sort_by<F: FnMut(&T, &T) -> Ordering>(&mut self, mut compare: F) {
+        let len = self.len();
+        self.head = merge_sort(self.head.take(), len, &mut compare);
+    }
+
+    /// Sort the elements by the keys `f` extracts from them, keeping the
+    /// order of elements with equal keys.
+    pub fn sort_by_key<K: Ord, F: FnMut(&T) -> K>(&mut self, mut f: F) {
+        self.sort_by(|a, b| f(a).cmp(&f(b)));
+    }
+}
+
+// Sort the `len` nodes from `link` on. The halves are sorted recursively,
+// which only goes O(log n) calls deep, and then merged in a loop.
+fn merge_sort<T, F: FnMut(&T, &T) -> Ordering>(mut link: Link<T>, len: usize, compare: &mut F) -> Link<T> {
+    if len < 2 {
+        return link;
+    }
+    let mut cur = &mut link;
+    for _ in 0..len / 2 {
+        cur = &mut cur.as_mut().unwrap().next;
+    }
+    let right = cur.take();
+    let left = merge_sort(link, len / 2, compare);
+    let right = merge_sort(right, len - len / 2, compare);
+    merge(left, right, compare)
+}
+
+// Merge two sorted lists. On ties the node from `left` goes first, which
+// keeps the sort stable.
+fn merge<T, F: FnMut(&T, &T) -> Ordering>(mut left: Link<T>, mut right: Link<T>, compare: &mut F) -> Link<T> {
+    let mut head = None;
+    let mut tail = &mut head;
+    while let (Some(l), Some(r)) = (left.as_ref(), right.as_ref()) {
+        let take_left = compare(&r.elem, &l.elem) != Ordering::Less;
+        let source = if take_left { &mut left } else { &mut right };
+        let mut node = source.take().unwrap();
+        *source = node.next.take();
+        tail = &mut tail.get_or_insert(node).next;
+    }
+    *tail = left.or(right);
+    head
 }
 
 impl<T> Drop for List<T> {
@@ -64,8 +203,11 @@ impl<T> Drop for List<T> {
 // useful for trivial wrappers around other types.
 pub struct IntoIter<T>(List<T>);
 
-impl<T> List<T> {
-    pub fn into_iter(self) -> IntoIter<T> {
+impl<T> IntoIterator for List<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
         IntoIter(self)
     }
 }
@@ -83,8 +225,17 @@ pub struct Iter<'a, T: 'a> {
 }
 
 impl<T> List<T> {
-    pub fn iter(&self) -> Iter<T> {
-        Iter { next: self.head.as_ref().map(|node| &**node) }
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { next: self.head.as_deref() }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a List<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
     }
 }
 
@@ -93,7 +244,7 @@ impl<'a, T> Iterator for Iter<'a, T> {
 
     fn next(&mut self) -> Option<Self::Item> {
         self.next.map(|node| {
-            self.next = node.next.as_ref().map(|node| &**node);
+            self.next = node.next.as_deref();
             &node.elem
         })
     }
@@ -104,8 +255,17 @@ pub struct IterMut<'a, T: 'a> {
 }
 
 impl<T> List<T> {
-    pub fn iter_mut(&mut self) -> IterMut<T> {
-        IterMut { next: self.head.as_mut().map(|node| &mut **node) }
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut { next: self.head.as_deref_mut() }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut List<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
     }
 }
 
@@ -114,12 +274,212 @@ impl<'a, T> Iterator for IterMut<'a, T> {
 
     fn next(&mut self) -> Option<Self::Item> {
         self.next.take().map(|node| {
-            self.next = node.next.as_mut().map(|node| &mut **node);
+            self.next = node.next.as_deref_mut();
             &mut node.elem
         })
     }
 }
 
+// ============================================== Cursor
+
+impl<T> List<T> {
+    /// Return a cursor at the ghost position before the head.
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut { at: Some(At::Ghost(&mut self.head)) }
+    }
+}
+
+/// A cursor which walks the list from the head on, and edits it right
+/// after the place it is at. It is either at an element or at the "ghost"
+/// position before the head, where it starts, so every element can be
+/// reached by the operations on what comes next. Each of them takes O(1)
+/// time, except `splice_after`.
+pub struct CursorMut<'a, T: 'a> {
+    // Only None while the cursor is being moved.
+    at: Option<At<'a, T>>,
+}
+
+enum At<'a, T: 'a> {
+    Ghost(&'a mut Link<T>),
+    Node(&'a mut Node<T>),
+}
+
+impl<'a, T> CursorMut<'a, T> {
+    // The link to whatever comes after the cursor.
+    fn next_link(&mut self) -> &mut Link<T> {
+        match *self.at.as_mut().unwrap() {
+            At::Ghost(ref mut head) => head,
+            At::Node(ref mut node) => &mut node.next
+        }
+    }
+
+    /// Return the element the cursor is at, or None at the ghost position.
+    pub fn current(&mut self) -> Option<&mut T> {
+        match *self.at.as_mut().unwrap() {
+            At::Ghost(_) => None,
+            At::Node(ref mut node) => Some(&mut node.elem)
+        }
+    }
+
+    /// Return the element after the cursor, or None if the cursor is at
+    /// the last element.
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        self.next_link().as_mut().map(|node| &mut node.elem)
+    }
+
+    /// Move the cursor to the next element. Return false, and stay, if the
+    /// cursor is at the last element.
+    pub fn move_next(&mut self) -> bool {
+        if self.next_link().is_none() {
+            return false;
+        }
+        let link = match self.at.take().unwrap() {
+            At::Ghost(head) => head,
+            At::Node(node) => &mut node.next
+        };
+        self.at = link.as_mut().map(|node| At::Node(&mut **node));
+        true
+    }
+
+    /// Insert an element right after the cursor, which stays where it is.
+    pub fn insert_after(&mut self, elem: T) {
+        let link = self.next_link();
+        let next = link.take();
+        *link = Some(Box::new(Node { elem, next }));
+    }
+
+    /// Remove the element right after the cursor and return it, or None if
+    /// the cursor is at the last element.
+    pub fn remove_after(&mut self) -> Option<T> {
+        let link = self.next_link();
+        link.take().map(|boxed_node| {
+            let node = *boxed_node;
+            *link = node.next;
+            node.elem
+        })
+    }
+
+    /// Cut the list after the cursor, and return everything that came
+    /// after it.
+    pub fn split_after(&mut self) -> List<T> {
+        List { head: self.next_link().take() }
+    }
+
+    /// Insert all the elements of `list` right after the cursor, keeping
+    /// their order. Takes time proportional to the length of `list`.
+    pub fn splice_after(&mut self, mut list: List<T>) {
+        let link = self.next_link();
+        list.append(&mut List { head: link.take() });
+        *link = list.head.take();
+    }
+}
+
+// ============================================== Traits
+
+impl<T: Clone> Clone for List<T> {
+    fn clone(&self) -> Self {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq> PartialEq for List<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for List<T> {}
+
+impl<T: Hash> Hash for List<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut len = 0;
+        for elem in self {
+            elem.hash(state);
+            len += 1;
+        }
+        state.write_usize(len);
+    }
+}
+
+// A collected list keeps the order of the iterator, with its first
+// element on top.
+impl<T> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = List::new();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T> Extend<T> for List<T> {
+    /// Add the elements after the last one of the list, in order.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut tail = &mut self.head;
+        while let Some(ref mut node) = *tail {
+            tail = &mut node.next;
+        }
+        for elem in iter {
+            tail = &mut tail.get_or_insert(Box::new(Node { elem, next: None })).next;
+        }
+    }
+}
+
+// A list is serialized as the sequence of its elements from the head on.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use std::fmt;
+    use std::iter;
+    use std::marker::PhantomData;
+
+    use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
+    use serde::ser::{Serialize, Serializer};
+
+    use super::List;
+
+    impl<T: Serialize> Serialize for List<T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.iter())
+        }
+    }
+
+    impl<'de, T: Deserialize<'de>> Deserialize<'de> for List<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_seq(ListVisitor(PhantomData))
+        }
+    }
+
+    struct ListVisitor<T>(PhantomData<fn() -> List<T>>);
+
+    impl<'de, T: Deserialize<'de>> Visitor<'de> for ListVisitor<T> {
+        type Value = List<T>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a sequence")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            // Extend walks to the tail once, so feed it the whole sequence,
+            // keeping the first error to return after it stops.
+            let mut error = None;
+            let mut list = List::new();
+            list.extend(iter::from_fn(|| seq.next_element().unwrap_or_else(|err| {
+                error = Some(err);
+                None
+            })));
+            if let Some(err) = error {
+                return Err(err);
+            }
+            Ok(list)
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::List;
@@ -196,4 +556,251 @@ mod test {
         assert_eq!(iter.next(), Some(&mut 2));
         assert_eq!(iter.next(), Some(&mut 1));
     }
-}
\ No newline at end of file
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        let list = {
+            let mut list = List::new();
+            list.push(1); list.push(2); list.push(3);
+            list
+        };
+        let json = serde_json::to_string(&list).unwrap();
+        assert_eq!(json, "[3,2,1]");
+
+        let list: List<i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(list.iter().cloned().collect::<Vec<_>>(), vec![3, 2, 1]);
+        assert_eq!(serde_json::from_str::<List<i32>>("[]").unwrap().peek(), None);
+        assert_eq!(serde_json::from_str::<List<i32>>("[1, \"two\"]").is_err(), true);
+    }
+
+    #[test]
+    fn traits() {
+        let mut list: List<i32> = vec![1, 2, 3].into_iter().collect();
+        assert_eq!(format!("{:?}", list), "[1, 2, 3]");
+        assert_eq!(list.len(), 3);
+
+        let copy = list.clone();
+        assert_eq!(copy, list);
+        list.extend(vec![4, 5]);
+        assert!(copy != list);
+
+        for elem in &mut list {
+            *elem *= 10;
+        }
+        let mut elems = Vec::new();
+        for elem in &list {
+            elems.push(*elem);
+        }
+        assert_eq!(elems, vec![10, 20, 30, 40, 50]);
+
+        list.clear();
+        assert!(list.is_empty());
+        assert_eq!(list.pop(), None);
+    }
+
+    fn list_of(elems: &[i32]) -> List<i32> {
+        elems.iter().cloned().collect()
+    }
+
+    fn elems_of<T: Clone>(list: &List<T>) -> Vec<T> {
+        list.iter().cloned().collect()
+    }
+
+    #[test]
+    fn reverse() {
+        let mut list = list_of(&[1, 2, 3, 4]);
+        list.reverse();
+        assert_eq!(elems_of(&list), vec![4, 3, 2, 1]);
+
+        let mut list = List::<i32>::new();
+        list.reverse();
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn split_off_and_append() {
+        let mut list = list_of(&[1, 2, 3, 4, 5]);
+        let mut back = list.split_off(2);
+        assert_eq!(elems_of(&list), vec![1, 2]);
+        assert_eq!(elems_of(&back), vec![3, 4, 5]);
+
+        // Splitting at the ends
+        assert!(back.split_off(3).is_empty());
+        let mut all = back.split_off(0);
+        assert!(back.is_empty());
+        assert_eq!(elems_of(&all), vec![3, 4, 5]);
+
+        list.append(&mut all);
+        assert!(all.is_empty());
+        assert_eq!(elems_of(&list), vec![1, 2, 3, 4, 5]);
+
+        // Appending onto an empty list, and an empty list
+        all.append(&mut list);
+        all.append(&mut list);
+        assert_eq!(elems_of(&all), vec![1, 2, 3, 4, 5]);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "split_off index out of bounds")]
+    fn split_off_out_of_bounds() {
+        list_of(&[1, 2]).split_off(3);
+    }
+
+    #[test]
+    fn contains_and_remove_first() {
+        let mut list = list_of(&[1, 2, 3, 2]);
+        assert!(list.contains(&3));
+        assert!(!list.contains(&4));
+
+        assert_eq!(list.remove_first(|&elem| elem == 2), Some(2));
+        assert_eq!(elems_of(&list), vec![1, 3, 2]);
+        assert_eq!(list.remove_first(|&elem| elem > 5), None);
+
+        // Removing the head and the last element
+        assert_eq!(list.remove_first(|&elem| elem == 1), Some(1));
+        assert_eq!(list.remove_first(|&elem| elem == 2), Some(2));
+        assert_eq!(elems_of(&list), vec![3]);
+        assert_eq!(list.remove_first(|_| true), Some(3));
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn sort() {
+        let mut list = list_of(&[5, 1, 4, 2, 3, 2]);
+        list.sort();
+        assert_eq!(elems_of(&list), vec![1, 2, 2, 3, 4, 5]);
+
+        list.sort_by(|a, b| b.cmp(a));
+        assert_eq!(elems_of(&list), vec![5, 4, 3, 2, 2, 1]);
+
+        // Check against Vec::sort, on a pseudo-random sequence
+        let elems: Vec<i32> = (0..1000).map(|i| (i * 7919) % 1009 - 500).collect();
+        let mut list = list_of(&elems);
+        let mut sorted = elems.clone();
+        sorted.sort();
+        list.sort();
+        assert_eq!(elems_of(&list), sorted);
+
+        let mut list = List::<i32>::new();
+        list.sort();
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn sort_is_stable() {
+        let pairs = vec![(2, 'a'), (1, 'b'), (2, 'c'), (1, 'd'), (0, 'e'), (2, 'f')];
+        let mut list: List<(i32, char)> = pairs.into_iter().collect();
+        list.sort_by_key(|pair| pair.0);
+        assert_eq!(elems_of(&list), vec![(0, 'e'), (1, 'b'), (1, 'd'), (2, 'a'), (2, 'c'), (2, 'f')]);
+    }
+
+    #[test]
+    fn sort_relinks_nodes() {
+        let mut list = list_of(&[3, 1, 2]);
+        let mut before: Vec<(i32, usize)> = list.iter().map(|elem| (*elem, elem as *const i32 as usize)).collect();
+        list.sort();
+        let after: Vec<(i32, usize)> = list.iter().map(|elem| (*elem, elem as *const i32 as usize)).collect();
+
+        // The elements stay where they are, only their order changes
+        before.sort();
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn cursor_walk() {
+        let mut list = list_of(&[1, 2, 3]);
+        let mut cursor = list.cursor_mut();
+
+        // The cursor starts before the head
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.peek_next(), Some(&mut 1));
+
+        assert!(cursor.move_next());
+        assert_eq!(cursor.current(), Some(&mut 1));
+        *cursor.peek_next().unwrap() *= 10;
+        assert!(cursor.move_next());
+        assert!(cursor.move_next());
+        assert_eq!(cursor.current(), Some(&mut 3));
+
+        // At the last element, the cursor stays
+        assert_eq!(cursor.peek_next(), None);
+        assert!(!cursor.move_next());
+        assert_eq!(cursor.current(), Some(&mut 3));
+        assert_eq!(elems_of(&list), vec![1, 20, 3]);
+
+        let mut empty = List::<i32>::new();
+        let mut cursor = empty.cursor_mut();
+        assert!(!cursor.move_next());
+        assert_eq!(cursor.remove_after(), None);
+    }
+
+    #[test]
+    fn cursor_ordered_insert() {
+        let mut list = List::new();
+        for &elem in &[5, 1, 4, 2, 3, 0, 6] {
+            let mut cursor = list.cursor_mut();
+            while cursor.peek_next().is_some_and(|next| *next < elem) {
+                cursor.move_next();
+            }
+            cursor.insert_after(elem);
+        }
+        assert_eq!(elems_of(&list), vec![0, 1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn cursor_filter() {
+        let mut list = list_of(&[2, 4, 1, 6, 3, 8, 8]);
+        {
+            let mut cursor = list.cursor_mut();
+            while let Some(&mut next) = cursor.peek_next() {
+                if next % 2 == 0 {
+                    assert_eq!(cursor.remove_after(), Some(next));
+                } else {
+                    cursor.move_next();
+                }
+            }
+        }
+        assert_eq!(elems_of(&list), vec![1, 3]);
+    }
+
+    #[test]
+    fn cursor_split_and_splice() {
+        let mut list = list_of(&[1, 2, 3, 4]);
+        let mut back = {
+            let mut cursor = list.cursor_mut();
+            cursor.move_next();
+            cursor.move_next();
+            cursor.split_after()
+        };
+        assert_eq!(elems_of(&list), vec![1, 2]);
+        assert_eq!(elems_of(&back), vec![3, 4]);
+
+        {
+            // Splice in the middle, and keep going from the same place
+            let mut cursor = list.cursor_mut();
+            cursor.move_next();
+            cursor.splice_after(list_of(&[10, 11]));
+            assert_eq!(cursor.peek_next(), Some(&mut 10));
+            cursor.splice_after(List::new());
+            assert_eq!(cursor.peek_next(), Some(&mut 10));
+        }
+        assert_eq!(elems_of(&list), vec![1, 10, 11, 2]);
+
+        // Splice at the front, and at the end
+        back.cursor_mut().splice_after(list_of(&[0]));
+        {
+            let mut cursor = back.cursor_mut();
+            while cursor.move_next() {}
+            cursor.splice_after(list_of(&[5, 6]));
+            assert!(cursor.move_next());
+        }
+        assert_eq!(elems_of(&back), vec![0, 3, 4, 5, 6]);
+
+        // Splitting before the head takes everything
+        let all = back.cursor_mut().split_after();
+        assert!(back.is_empty());
+        assert_eq!(all.len(), 5);
+    }
+}
@@ -0,0 +1,47 @@
+use third::{self, ArcKind};
+
+/// The persistent list of `third`, with its nodes shared through `Arc`
+/// rather than `Rc`. So a list is `Send` and `Sync` when its elements are,
+/// and versions of it can be handed to other threads while this one keeps
+/// using them. An empty list is made with `List::default()`.
+pub type List<T> = third::List<T, ArcKind>;
+
+#[cfg(test)]
+mod test {
+    use super::List;
+    use std::thread;
+
+    #[test]
+    fn send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<List<i32>>();
+
+        let list: List<i32> = (0..100).collect();
+        let handles: Vec<_> = (0..4).map(|i| {
+            // Every thread gets its own version, sharing the same nodes
+            let list = list.drop(i * 10);
+            thread::spawn(move || list.fold(0, |sum, elem| sum + elem))
+        }).collect();
+        let sums: Vec<i32> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+        assert_eq!(sums, vec![4950, 4905, 4760, 4515]);
+        assert_eq!(list.len(), 100);
+    }
+
+    #[test]
+    fn basics() {
+        let list = List::default().append(1).append(2);
+        assert_eq!(list.head(), Some(&2));
+        assert_eq!(list.tail().head(), Some(&1));
+        assert_eq!(list.tail().tail().head(), None);
+    }
+
+    #[test]
+    fn long_drop() {
+        // Dropping node by node recursively would overflow the stack
+        let len = if cfg!(miri) { 1000 } else { 1_000_000 };
+        let list: List<i32> = (0..len).collect();
+        let half = list.drop(len as usize / 2);
+        drop(list);
+        assert_eq!(half.head(), Some(&(len / 2)));
+    }
+}
@@ -1,70 +1,323 @@
 
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::Deref;
 use std::rc::Rc;
+use std::sync::Arc;
 
-pub struct List<T> {
-    head: Link<T>,
+/// The kind of reference-counted pointer which links the nodes of a list
+/// together.
+pub trait PointerKind {
+    type Pointer<U>: Clone + Deref<Target = U>;
+    fn new<U>(value: U) -> Self::Pointer<U>;
+
+    /// Return the value if `pointer` is the last one to it, or None.
+    fn try_unwrap<U>(pointer: Self::Pointer<U>) -> Option<U>;
+}
+
+/// Link nodes with `Rc`, which is cheap but keeps the list on one thread.
+#[derive(Debug)]
+pub enum RcKind {}
+
+impl PointerKind for RcKind {
+    type Pointer<U> = Rc<U>;
+    fn new<U>(value: U) -> Rc<U> {
+        Rc::new(value)
+    }
+
+    fn try_unwrap<U>(pointer: Rc<U>) -> Option<U> {
+        Rc::try_unwrap(pointer).ok()
+    }
+}
+
+/// Link nodes with `Arc`, so versions of a list can be shared across
+/// threads.
+#[derive(Debug)]
+pub enum ArcKind {}
+
+impl PointerKind for ArcKind {
+    type Pointer<U> = Arc<U>;
+    fn new<U>(value: U) -> Arc<U> {
+        Arc::new(value)
+    }
+
+    fn try_unwrap<U>(pointer: Arc<U>) -> Option<U> {
+        Arc::try_unwrap(pointer).ok()
+    }
 }
 
-type Link<T> = Option<Rc<Node<T>>>;
+pub struct List<T, P: PointerKind = RcKind> {
+    head: Link<T, P>,
+}
 
-struct Node<T> {
+type Link<T, P> = Option<<P as PointerKind>::Pointer<Node<T, P>>>;
+
+struct Node<T, P: PointerKind> {
     elem: T,
-    next: Link<T>,
+    next: Link<T, P>,
+}
+
+// An empty list of any pointer kind. Only `Rc` lists have `new`, so that
+// `List::new()` does not need the kind spelled out.
+impl<T, P: PointerKind> Default for List<T, P> {
+    fn default() -> Self {
+        List { head: None }
+    }
 }
 
 impl<T> List<T> {
     pub fn new() -> Self {
         List { head: None }
     }
+}
 
-    pub fn append(&self, elem: T) -> List<T> {
-        List { head: Some(Rc::new(Node {
-            elem: elem,
+impl<T, P: PointerKind> List<T, P> {
+    pub fn append(&self, elem: T) -> List<T, P> {
+        List { head: Some(P::new(Node {
+            elem,
             next: self.head.clone(),
         }))}
     }
 
-    pub fn tail(&self) -> List<T> {
+    pub fn tail(&self) -> List<T, P> {
         List { head: self.head.as_ref().and_then(|node| node.next.clone()) }
     }
 
     pub fn head(&self) -> Option<&T> {
         self.head.as_ref().map(|node| &node.elem )
     }
+
+    /// Return the number of elements. Takes O(n) time.
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+
+    /// Let go of the elements. Other lists sharing them keep theirs.
+    pub fn clear(&mut self) {
+        *self = List::default();
+    }
+}
+
+// ============================================== Functional operations
+
+// None of these change the list they are called on. The nodes of a result
+// are shared with the list wherever the result ends the same way, and new
+// ones are only made for the elements in front of that.
+
+impl<T, P: PointerKind> List<T, P> {
+    /// Return the element at index `n`, or None if the list is shorter.
+    pub fn nth(&self, n: usize) -> Option<&T> {
+        self.iter().nth(n)
+    }
+
+    /// Combine the elements from the head on into a single value.
+    pub fn fold<B, F: FnMut(B, &T) -> B>(&self, init: B, f: F) -> B {
+        self.iter().fold(init, f)
+    }
+
+    /// Return a list of what `f` gives for each element.
+    pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> List<U, P> {
+        self.iter().map(f).collect()
+    }
+
+    /// Return the list without its first `n` elements, sharing all the
+    /// rest. Takes O(n) time.
+    pub fn drop(&self, n: usize) -> List<T, P> {
+        let mut link = &self.head;
+        for _ in 0..n {
+            match *link {
+                Some(ref node) => link = &node.next,
+                None => break
+            }
+        }
+        List { head: link.clone() }
+    }
+
+    /// Return the list of its first `n` elements. The whole list is shared
+    /// if it has no more than `n`.
+    pub fn take(&self, n: usize) -> List<T, P> where T: Clone {
+        if self.iter().nth(n).is_none() {
+            return self.clone();
+        }
+        self.iter().take(n).cloned().collect()
+    }
+
+    /// Return the list of the elements for which `pred` is true. The part
+    /// after the last element left out is shared.
+    pub fn filter<F: FnMut(&T) -> bool>(&self, mut pred: F) -> List<T, P> where T: Clone {
+        let mut kept = Vec::new();
+        // The shared part, and how many of the kept elements come before it
+        let mut suffix = &self.head;
+        let mut kept_before = 0;
+        let mut link = &self.head;
+        while let Some(ref node) = *link {
+            if pred(&node.elem) {
+                kept.push(&node.elem);
+            } else {
+                suffix = &node.next;
+                kept_before = kept.len();
+            }
+            link = &node.next;
+        }
+        prepend(kept[..kept_before].iter().map(|&elem| elem.clone()), List { head: suffix.clone() })
+    }
+
+    /// Return the elements in reverse order.
+    pub fn reverse(&self) -> List<T, P> where T: Clone {
+        self.fold(List::default(), |list, elem| list.append(elem.clone()))
+    }
+
+    /// Return the elements of the list followed by those of `other`, which
+    /// are shared.
+    pub fn concat(&self, other: &List<T, P>) -> List<T, P> where T: Clone {
+        prepend(self.iter().cloned(), other.clone())
+    }
+
+    /// Return the list of pairs of elements at the same index, as long as
+    /// the shorter of the two lists.
+    pub fn zip<U: Clone>(&self, other: &List<U, P>) -> List<(T, U), P> where T: Clone {
+        self.iter().cloned().zip(other.iter().cloned()).collect()
+    }
 }
 
-pub struct Iter<'a, T:'a> {
-    next: Option<&'a Node<T>>,
+// Put the elements in front of `list`, keeping their order.
+fn prepend<T, P: PointerKind, I: Iterator<Item = T>>(elems: I, list: List<T, P>) -> List<T, P> {
+    let elems: Vec<T> = elems.collect();
+    elems.into_iter().rev().fold(list, |list, elem| list.append(elem))
 }
 
-impl<T> List<T> {
-    pub fn iter<'a>(&'a self) -> Iter<'a, T> {
-        Iter { next: self.head.as_ref().map(|node| &**node) }
+pub struct Iter<'a, T: 'a, P: PointerKind + 'a = RcKind> {
+    next: Option<&'a Node<T, P>>,
+}
+
+impl<T, P: PointerKind> List<T, P> {
+    pub fn iter<'a>(&'a self) -> Iter<'a, T, P> {
+        Iter { next: self.head.as_deref() }
     }
 }
 
-impl<'a, T> Iterator for Iter<'a, T> {
+impl<'a, T, P: PointerKind> Iterator for Iter<'a, T, P> {
     type Item = &'a T;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.next.map(|node| {
-            self.next = node.next.as_ref().map(|node| &**node);
+            self.next = node.next.as_deref();
             &node.elem
         })
     }
 }
 
-impl<T> Drop for List<T> {
+impl<T, P: PointerKind> Drop for List<T, P> {
     fn drop(&mut self) {
-        // Steal the list's head
-        let mut cur_list = self.head.take();
-        while let Some(node) = cur_list {
-            // Clone the current node's next node.
-            cur_list = node.next.clone();
-            // Node dropped here. If the old node had
-            // refcount 1, then it will be dropped and freed, but it won't
-            // be able to fully recurse and drop its child, because we
-            // hold another Rc to it.
+        // Free the nodes this list is the last owner of, one at a time so a
+        // long list does not overflow the stack. The first node which is
+        // still shared keeps the rest alive, so there is no need to go on.
+        let mut head = self.head.take();
+        while let Some(node) = head {
+            match P::try_unwrap(node) {
+                Some(mut node) => head = node.next.take(),
+                None => break
+            }
+        }
+    }
+}
+
+// ============================================== Traits
+
+// Cloning only shares the head, like `tail` does.
+impl<T, P: PointerKind> Clone for List<T, P> {
+    fn clone(&self) -> Self {
+        List { head: self.head.clone() }
+    }
+}
+
+impl<T: fmt::Debug, P: PointerKind> fmt::Debug for List<T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, P: PointerKind> PartialEq for List<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq, P: PointerKind> Eq for List<T, P> {}
+
+impl<T: Hash, P: PointerKind> Hash for List<T, P> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut len = 0;
+        for elem in self.iter() {
+            elem.hash(state);
+            len += 1;
+        }
+        state.write_usize(len);
+    }
+}
+
+// A collected list keeps the order of the iterator, with its first
+// element at the head.
+impl<T, P: PointerKind> FromIterator<T> for List<T, P> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        prepend(iter.into_iter(), List::default())
+    }
+}
+
+impl<T: Clone, P: PointerKind> Extend<T> for List<T, P> {
+    /// Add the elements after the last one of the list, in order. The
+    /// nodes are shared, so the elements of the list are copied into new
+    /// ones.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        *self = prepend(self.iter().cloned().chain(iter), List::default());
+    }
+}
+
+// A list is serialized as the sequence of its elements from the head on.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use std::fmt;
+    use std::marker::PhantomData;
+
+    use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
+    use serde::ser::{Serialize, Serializer};
+
+    use super::{List, PointerKind};
+
+    impl<T: Serialize, P: PointerKind> Serialize for List<T, P> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.iter())
+        }
+    }
+
+    impl<'de, T: Deserialize<'de>, P: PointerKind> Deserialize<'de> for List<T, P> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_seq(ListVisitor(PhantomData))
+        }
+    }
+
+    struct ListVisitor<T, P: PointerKind>(PhantomData<fn() -> List<T, P>>);
+
+    impl<'de, T: Deserialize<'de>, P: PointerKind> Visitor<'de> for ListVisitor<T, P> {
+        type Value = List<T, P>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a sequence")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            // Elements can only be added at the head, so the last one goes
+            // in first.
+            let mut elems: Vec<T> = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+            while let Some(elem) = seq.next_element()? {
+                elems.push(elem);
+            }
+            Ok(elems.into_iter().rev().fold(List::default(), |list, elem| list.append(elem)))
         }
     }
 }
@@ -72,6 +325,8 @@ impl<T> Drop for List<T> {
 #[cfg(test)]
 mod test {
     use super::List;
+    use std::ptr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[test]
     fn basics() {
@@ -105,4 +360,145 @@ mod test {
         assert_eq!(iter.next(), Some(&2));
         assert_eq!(iter.next(), Some(&1));
     }
-}
\ No newline at end of file
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        let list = List::new().append(1).append(2).append(3);
+        let json = serde_json::to_string(&list).unwrap();
+        assert_eq!(json, "[3,2,1]");
+
+        let list: List<i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(list.iter().cloned().collect::<Vec<_>>(), vec![3, 2, 1]);
+        assert_eq!(serde_json::from_str::<List<i32>>("[]").unwrap().head(), None);
+    }
+
+    #[test]
+    fn traits() {
+        let mut list: List<i32> = vec![1, 2, 3].into_iter().collect();
+        assert_eq!(format!("{:?}", list), "[1, 2, 3]");
+        assert_eq!(list.len(), 3);
+
+        let copy = list.clone();
+        assert_eq!(copy, list);
+        list.extend(vec![4, 5]);
+        assert_eq!(list.iter().cloned().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+
+        // The copy is not affected
+        assert_eq!(copy.len(), 3);
+        assert!(copy != list);
+
+        list.clear();
+        assert!(list.is_empty());
+        assert_eq!(copy.head(), Some(&1));
+    }
+
+    fn elems_of<T: Clone>(list: &List<T>) -> Vec<T> {
+        list.iter().cloned().collect()
+    }
+
+    // Return true if both lists start at the same node.
+    fn shares<T>(list: &List<T>, other: &List<T>) -> bool {
+        match (list.head(), other.head()) {
+            (Some(a), Some(b)) => ptr::eq(a, b),
+            _ => false
+        }
+    }
+
+    #[test]
+    fn nth_fold_map() {
+        let list: List<i32> = vec![1, 2, 3].into_iter().collect();
+        assert_eq!(list.nth(0), Some(&1));
+        assert_eq!(list.nth(2), Some(&3));
+        assert_eq!(list.nth(3), None);
+
+        assert_eq!(list.fold(0, |sum, elem| sum + elem), 6);
+        assert_eq!(elems_of(&list.map(|elem| elem.to_string())), vec!["1", "2", "3"]);
+        assert_eq!(List::<i32>::new().map(|elem| elem * 2), List::new());
+    }
+
+    #[test]
+    fn take_and_drop() {
+        let list: List<i32> = vec![1, 2, 3, 4].into_iter().collect();
+
+        let back = list.drop(2);
+        assert_eq!(elems_of(&back), vec![3, 4]);
+        assert!(shares(&back, &list.tail().tail()));
+        assert!(list.drop(4).is_empty());
+        assert!(list.drop(10).is_empty());
+        assert!(shares(&list.drop(0), &list));
+
+        assert_eq!(elems_of(&list.take(2)), vec![1, 2]);
+        assert!(list.take(0).is_empty());
+        assert!(shares(&list.take(4), &list));
+        assert!(shares(&list.take(10), &list));
+
+        // The original list is not affected
+        assert_eq!(elems_of(&list), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn filter() {
+        let list: List<i32> = vec![1, 2, 3, 4, 6, 8].into_iter().collect();
+
+        let evens = list.filter(|elem| elem % 2 == 0);
+        assert_eq!(elems_of(&evens), vec![2, 4, 6, 8]);
+
+        // Everything after the 3 is shared
+        assert!(shares(&evens.tail(), &list.drop(3)));
+        assert!(shares(&list.filter(|_| true), &list));
+        assert!(list.filter(|_| false).is_empty());
+        assert_eq!(elems_of(&list), vec![1, 2, 3, 4, 6, 8]);
+    }
+
+    #[test]
+    fn reverse_concat_zip() {
+        let list: List<i32> = vec![1, 2, 3].into_iter().collect();
+        assert_eq!(elems_of(&list.reverse()), vec![3, 2, 1]);
+        assert!(List::<i32>::new().reverse().is_empty());
+
+        let other: List<i32> = vec![4, 5].into_iter().collect();
+        let both = list.concat(&other);
+        assert_eq!(elems_of(&both), vec![1, 2, 3, 4, 5]);
+        assert!(shares(&both.drop(3), &other));
+        assert!(shares(&List::new().concat(&other), &other));
+
+        let pairs = list.zip(&other.map(|elem| elem.to_string()));
+        assert_eq!(elems_of(&pairs), vec![(1, "4".to_string()), (2, "5".to_string())]);
+        assert!(list.zip(&List::<i32>::new()).is_empty());
+    }
+
+    #[test]
+    fn drop_stops_at_shared_nodes() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Counted;
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let shared: List<Counted> = (0..3).map(|_| Counted).collect();
+        let list = shared.append(Counted).append(Counted);
+
+        // Only the two nodes in front of the shared ones are freed
+        drop(list);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 2);
+        assert_eq!(shared.len(), 3);
+
+        drop(shared);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn long_drop() {
+        // Dropping node by node recursively would overflow the stack
+        let len = if cfg!(miri) { 1000 } else { 1_000_000 };
+        let list: List<i32> = (0..len).collect();
+        let half = list.drop(len as usize / 2);
+        drop(list);
+        assert_eq!(half.head(), Some(&(len / 2)));
+    }
+}